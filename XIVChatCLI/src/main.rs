@@ -0,0 +1,314 @@
+//! Command line client for XIVChatBridge, for piping the chat into other tools.
+
+use std::{
+    io::{self, Write},
+    process::ExitCode,
+    time::Duration,
+};
+
+use clap::{Parser, Subcommand};
+use xivchat_client::{
+    models::{merge_messages, ChatMessage, ChatType, NewMessageRequest, CROSS_WORLD_ICON},
+    Client, ErrorKind, ErrorMessage, DEFAULT_URL,
+};
+
+const EXIT_CODES: &str = "Exit codes:
+  2  invalid arguments or input
+  3  the bridge could not be reached
+  4  the access token was rejected
+  5  the bridge answered with an error status
+  6  the response could not be parsed";
+
+#[derive(Parser)]
+#[command(name = "xivchat", about, after_help = EXIT_CODES)]
+struct Args {
+    /// Address of the bridge
+    #[arg(long, global = true, default_value = DEFAULT_URL)]
+    url: String,
+    /// Access token, if the bridge requires one
+    #[arg(long, global = true, env = "XIVCHAT_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the latest messages
+    Tail {
+        /// Only print messages of this channel, may be given multiple times
+        #[arg(short, long = "channel", value_parser = parse_channel)]
+        channels: Vec<Channel>,
+        /// Number of messages from the backlog to print
+        #[arg(short = 'n', long, default_value_t = 10)]
+        lines: usize,
+        /// Keep printing new messages as they arrive
+        #[arg(short, long)]
+        follow: bool,
+        /// Seconds between polls with --follow
+        #[arg(long, default_value_t = 3)]
+        interval: u64,
+        /// Print every message as a line of JSON in the plugin's format
+        #[arg(long)]
+        json: bool,
+    },
+    /// Send a message
+    Send {
+        /// Channel to send to, e.g. say, fc, party, ls1 or cwls1
+        #[arg(short, long, value_parser = parse_channel)]
+        channel: Channel,
+        /// Recipient of a tell, First Last@World
+        #[arg(long)]
+        to: Option<String>,
+        text: String,
+    },
+}
+
+/// The chat types a `--channel` argument stands for.
+#[derive(Clone, Debug, PartialEq)]
+struct Channel(Vec<ChatType>);
+
+/// Resolves a channel given as the frontend's label (`FC`, `LS1`), the plugin's `InputChannel`
+/// name (`freeCompany`, `linkshell1`) or its `XivChatType` name (`npcDialogue`). `tell` and
+/// `party` include incoming tells and cross world parties, sendable types come first.
+fn parse_channel(name: &str) -> Result<Channel, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "tell" => {
+            return Ok(Channel(vec![
+                ChatType::TellOutgoing,
+                ChatType::TellIncoming,
+            ]))
+        }
+        "party" => return Ok(Channel(vec![ChatType::Party, ChatType::CrossParty])),
+        _ => {}
+    }
+
+    ChatType::from_input_channel(name)
+        .or_else(|| {
+            ChatType::SENDABLE
+                .into_iter()
+                .find(|chat_type| chat_type.to_string().eq_ignore_ascii_case(name))
+        })
+        .or_else(|| {
+            serde_json::from_value(serde_json::Value::String(name.to_owned()))
+                .ok()
+                .filter(|chat_type| !matches!(chat_type, ChatType::Unimplemented(_)))
+        })
+        .map(|chat_type| Channel(vec![chat_type]))
+        .ok_or_else(|| format!("unknown channel \"{name}\""))
+}
+
+fn exit_code(kind: ErrorKind) -> ExitCode {
+    ExitCode::from(match kind {
+        ErrorKind::Input => 2,
+        ErrorKind::Connection => 3,
+        ErrorKind::Unauthorized => 4,
+        ErrorKind::Http => 5,
+        ErrorKind::Parse => 6,
+        ErrorKind::Rejected => 7,
+    })
+}
+
+fn format_message(msg: &ChatMessage) -> String {
+    let sender = msg.sender_name.replace(CROSS_WORLD_ICON, "@");
+    let sender = if sender.is_empty() {
+        sender
+    } else {
+        format!(" {sender}:")
+    };
+    format!(
+        "[{}] [{}]{sender} {}",
+        msg.formatted_timestamp(),
+        msg.chat_type,
+        msg.text
+    )
+}
+
+fn print_messages<'a>(
+    messages: impl Iterator<Item = &'a ChatMessage>,
+    json: bool,
+) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    for msg in messages {
+        if json {
+            serde_json::to_writer(&mut out, msg)?;
+            writeln!(out)?;
+        } else {
+            writeln!(out, "{}", format_message(msg))?;
+        }
+    }
+    out.flush()
+}
+
+async fn tail(
+    client: &Client,
+    channels: &[ChatType],
+    lines: usize,
+    follow: bool,
+    interval: Duration,
+    json: bool,
+) -> Result<(), ErrorMessage> {
+    let shown = |msg: &&ChatMessage| channels.is_empty() || channels.contains(&msg.chat_type);
+    let print = |messages: &[ChatMessage]| {
+        match print_messages(messages.iter().filter(shown), json) {
+            // a closed pipe, e.g. from `head`, just ends the output
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => std::process::exit(0),
+            Err(e) => Err(ErrorMessage {
+                kind: ErrorKind::Input,
+                description: "Unable to write output".to_owned(),
+                details: Some(e.to_string()),
+            }),
+            Ok(()) => Ok(()),
+        }
+    };
+
+    let mut messages = client.get_messages(None).await?;
+    let backlog: Vec<ChatMessage> = messages.iter().filter(shown).cloned().collect();
+    print(&backlog[backlog.len().saturating_sub(lines)..])?;
+
+    if !follow {
+        return Ok(());
+    }
+    loop {
+        tokio::time::sleep(interval).await;
+        // only the newest message is needed as cursor
+        messages.drain(..messages.len().saturating_sub(1));
+        let since = messages.last().map(|msg| msg.timestamp.clone());
+        match client.get_messages(since.as_deref()).await {
+            Ok(fetched) => {
+                let new = merge_messages(&mut messages, fetched, since.as_deref());
+                print(&messages[messages.len() - new..])?;
+            }
+            // the game may just be restarting
+            Err(e) if e.kind == ErrorKind::Connection => eprintln!("{e}"),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn send(
+    client: &Client,
+    channel: &Channel,
+    to: Option<&str>,
+    text: String,
+) -> Result<(), ErrorMessage> {
+    let input_error = |description: String| ErrorMessage {
+        kind: ErrorKind::Input,
+        description,
+        details: None,
+    };
+
+    let chat_type = channel
+        .0
+        .iter()
+        .find(|chat_type| chat_type.input_channel().is_some())
+        .cloned()
+        .ok_or_else(|| input_error("Messages can not be sent to this channel".to_owned()))?;
+    let target = match (&chat_type, to) {
+        (ChatType::TellOutgoing, Some(to)) => Some(to.parse().map_err(input_error)?),
+        (ChatType::TellOutgoing, None) => {
+            return Err(input_error("Tells need a recipient, see --to".to_owned()))
+        }
+        _ => None,
+    };
+
+    client
+        .send_message(&NewMessageRequest {
+            chat_type,
+            text,
+            target,
+        })
+        .await
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let result = match Client::new(&args.url, args.token) {
+        Ok(client) => match args.command {
+            Command::Tail {
+                channels,
+                lines,
+                follow,
+                interval,
+                json,
+            } => {
+                let channels: Vec<ChatType> =
+                    channels.into_iter().flat_map(|channel| channel.0).collect();
+                let interval = Duration::from_secs(interval.max(1));
+                tail(&client, &channels, lines, follow, interval, json).await
+            }
+            Command::Send { channel, to, text } => {
+                send(&client, &channel, to.as_deref(), text).await
+            }
+        },
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            exit_code(e.kind)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use xivchat_client::models::{ChatMessage, ChatType};
+
+    use super::{format_message, parse_channel, Channel};
+
+    #[test]
+    fn test_parse_channel() {
+        assert_eq!(
+            parse_channel("fc"),
+            Ok(Channel(vec![ChatType::FreeCompany]))
+        );
+        assert_eq!(
+            parse_channel("freeCompany"),
+            Ok(Channel(vec![ChatType::FreeCompany]))
+        );
+        assert_eq!(
+            parse_channel("LS3"),
+            Ok(Channel(vec![ChatType::LinkShell3]))
+        );
+        assert_eq!(
+            parse_channel("crossLinkshell2"),
+            Ok(Channel(vec![ChatType::CrossLinkShell2]))
+        );
+        assert_eq!(
+            parse_channel("npcDialogue"),
+            Ok(Channel(vec![ChatType::NPCDialogue]))
+        );
+        assert_eq!(
+            parse_channel("Tell"),
+            Ok(Channel(vec![
+                ChatType::TellOutgoing,
+                ChatType::TellIncoming
+            ]))
+        );
+        assert!(parse_channel("nope").is_err());
+    }
+
+    #[test]
+    fn test_format_message() {
+        let msg = ChatMessage {
+            timestamp: "2024-07-14T10:18:02.8379913+02:00".to_owned(),
+            chat_type: ChatType::FreeCompany,
+            sender_name: "Alpha Beta\u{e05d}Phoenix".to_owned(),
+            text: "hi".to_owned(),
+            payloads: None,
+            server_id: None,
+            source: None,
+        };
+        assert_eq!(
+            format_message(&msg),
+            format!(
+                "[{}] [FC] Alpha Beta@Phoenix: hi",
+                msg.formatted_timestamp()
+            )
+        );
+    }
+}
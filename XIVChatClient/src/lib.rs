@@ -0,0 +1,243 @@
+//! Native counterpart of the frontend's `requests` module, shared by the terminal clients.
+
+use std::{error::Error, fmt::Display};
+
+use serde::de;
+use xivchat_protocol::{ChatMessage, NewMessageRequest};
+
+pub use xivchat_protocol as models;
+
+pub const DEFAULT_URL: &str = "http://localhost:9876";
+const MESSAGES_URI: &str = "/messages";
+
+#[derive(Clone, Debug)]
+pub struct ErrorMessage {
+    pub kind: ErrorKind,
+    pub description: String,
+    pub details: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// The request could not be made or got no response.
+    Connection,
+    /// The bridge rejected the access token.
+    Unauthorized,
+    /// The bridge refused to accept a message.
+    Rejected,
+    /// Any other unsuccessful status code.
+    Http,
+    /// The response could not be read or parsed.
+    Parse,
+    /// Invalid user input, nothing was sent.
+    Input,
+}
+
+impl Display for ErrorMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.details {
+            Some(details) if !details.is_empty() => write!(f, "{}: {details}", self.description),
+            _ => write!(f, "{}", self.description),
+        }
+    }
+}
+
+impl Error for ErrorMessage {}
+
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl Client {
+    /// Creates a client for the bridge at `base_url`, e.g. `http://localhost:9876`.
+    pub fn new(base_url: &str, token: Option<String>) -> Result<Self, ErrorMessage> {
+        let base_url = base_url.trim_end_matches('/').to_owned();
+        reqwest::Url::parse(&base_url)
+            .map_err(|e| e.to_string())
+            .and_then(|url| match url.scheme() {
+                "http" | "https" => Ok(()),
+                _ => Err("expected a http or https URL".to_owned()),
+            })
+            .map_err(|e| ErrorMessage {
+                kind: ErrorKind::Input,
+                description: format!("Invalid URL \"{base_url}\""),
+                details: Some(e),
+            })?;
+        Ok(Self {
+            http: reqwest::Client::new(),
+            base_url,
+            token,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, uri: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, format!("{}{uri}", self.base_url));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Fetches the message backlog. With a `since` timestamp only newer messages are requested,
+    /// but servers without cursor support will still return everything.
+    pub async fn get_messages(
+        &self,
+        since: Option<&str>,
+    ) -> Result<Vec<ChatMessage>, ErrorMessage> {
+        let mut request = self.request(reqwest::Method::GET, MESSAGES_URI);
+        if let Some(since) = since {
+            request = request.query(&[("since", since)]);
+        }
+        let response = request.send().await.map_err(|e| ErrorMessage {
+            kind: ErrorKind::Connection,
+            description: "Unable to get messages from Server. Make sure it is running!".to_owned(),
+            details: e.source().map(|source| source.to_string()),
+        })?;
+        from_response::<Vec<ChatMessage>>(response).await
+    }
+
+    pub async fn send_message(&self, msg: &NewMessageRequest) -> Result<(), ErrorMessage> {
+        let response = self
+            .request(reqwest::Method::POST, MESSAGES_URI)
+            .json(msg)
+            .send()
+            .await
+            .map_err(|e| ErrorMessage {
+                kind: ErrorKind::Connection,
+                description: "Unable to send message to Server. Make sure it is running!"
+                    .to_owned(),
+                details: e.source().map(|source| source.to_string()),
+            })?;
+
+        match response.status().as_u16() {
+            200..=299 => Ok(()),
+            401 | 403 => Err(unauthorized()),
+            400 => Err(ErrorMessage {
+                kind: ErrorKind::Rejected,
+                description: format!("Bad request: {}", response.text().await.unwrap_or_default()),
+                details: None,
+            }),
+            unknown_code => Err(ErrorMessage {
+                kind: ErrorKind::Http,
+                description: format!("unexpected response: {}", unknown_code),
+                details: None,
+            }),
+        }
+    }
+}
+
+fn unauthorized() -> ErrorMessage {
+    ErrorMessage {
+        kind: ErrorKind::Unauthorized,
+        description: "Access denied. Please provide a valid access token.".to_owned(),
+        details: None,
+    }
+}
+
+async fn from_response<T>(value: reqwest::Response) -> Result<T, ErrorMessage>
+where
+    T: de::DeserializeOwned,
+{
+    match value.status().as_u16() {
+        200..=299 => {}
+        401 | 403 => return Err(unauthorized()),
+        _ => {
+            return Err(ErrorMessage {
+                kind: ErrorKind::Http,
+                description: format!("HTTP Error: {error_code}", error_code = value.status()),
+                details: value.text().await.ok(),
+            })
+        }
+    }
+
+    let bytes = value.text().await.map_err(|e| ErrorMessage {
+        kind: ErrorKind::Parse,
+        description: "Unable to read response".to_owned(),
+        details: Some(e.to_string()),
+    })?;
+
+    serde_json::from_str(&bytes).map_err(|e| ErrorMessage {
+        kind: ErrorKind::Parse,
+        description: "JSON parsing failed".to_owned(),
+        details: Some(e.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use xivchat_protocol::{ChatType, NewMessageRequest};
+
+    use super::{Client, ErrorKind};
+
+    /// Answers a single request with `response` and returns the bridge URL.
+    async fn serve_once(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 4096];
+            let _ = stream.read(&mut buf).await.unwrap();
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+        format!("http://{addr}/")
+    }
+
+    async fn get_error(response: &'static str) -> ErrorKind {
+        let client = Client::new(&serve_once(response).await, None).unwrap();
+        client.get_messages(None).await.unwrap_err().kind
+    }
+
+    #[tokio::test]
+    async fn test_error_kinds() {
+        assert_eq!(
+            get_error("HTTP/1.1 401 Unauthorized\r\ncontent-length: 0\r\n\r\n").await,
+            ErrorKind::Unauthorized
+        );
+        assert_eq!(
+            get_error(
+                "HTTP/1.1 400 Bad Request\r\ncontent-length: 22\r\n\r\nUnknown request method"
+            )
+            .await,
+            ErrorKind::Http
+        );
+        assert_eq!(
+            get_error("HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n{}").await,
+            ErrorKind::Parse
+        );
+
+        let client = Client::new(
+            &serve_once("HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n[]").await,
+            None,
+        )
+        .unwrap();
+        assert!(client.get_messages(None).await.unwrap().is_empty());
+
+        // like in the web UI, only a refused message is rejected
+        let client = Client::new(
+            &serve_once("HTTP/1.1 400 Bad Request\r\ncontent-length: 4\r\n\r\nnope").await,
+            None,
+        )
+        .unwrap();
+        let request = NewMessageRequest {
+            chat_type: ChatType::Say,
+            text: "hello".to_owned(),
+            target: None,
+        };
+        assert_eq!(
+            client.send_message(&request).await.err().map(|e| e.kind),
+            Some(ErrorKind::Rejected)
+        );
+
+        assert_eq!(
+            Client::new("localhost:9876", None).err().map(|e| e.kind),
+            Some(ErrorKind::Input)
+        );
+    }
+}
@@ -0,0 +1,413 @@
+//! Stand-in for the XIVChatBridge plugin's HTTP server. It implements the same `/messages`
+//! contract, plus the `since` cursor and the `/messages/stream` endpoint the frontend can use,
+//! and fills the chat with synthetic traffic.
+
+mod models;
+mod traffic;
+
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::get,
+    Json, Router,
+};
+use clap::Parser;
+use models::{message_now, NewMessageRequest};
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tower_http::{cors::CorsLayer, services::ServeDir};
+use traffic::Generator;
+use xivchat_protocol::{ChatCommand, ChatMessage, ChatType, CROSS_WORLD_ICON};
+
+#[derive(Parser)]
+#[command(about)]
+struct Args {
+    /// Port to listen on
+    #[arg(long, default_value_t = 9876)]
+    port: u16,
+    /// Seconds between synthetic messages, 0 disables them
+    #[arg(long, default_value_t = 2.0)]
+    interval: f64,
+    /// Maximum number of kept messages, like the plugin's MessageLimit
+    #[arg(long, default_value_t = 5000)]
+    limit: usize,
+    /// Name posted messages are echoed back with
+    #[arg(long, default_value = "Mock Player")]
+    player: String,
+    /// Require this access token as bearer token
+    #[arg(long)]
+    token: Option<String>,
+    /// Serve the built frontend from this directory, e.g. XIVChatUI/dist
+    #[arg(long)]
+    frontend: Option<PathBuf>,
+}
+
+struct Bridge {
+    messages: Mutex<VecDeque<ChatMessage>>,
+    limit: usize,
+    player: String,
+    token: Option<String>,
+    new_messages: broadcast::Sender<ChatMessage>,
+}
+
+type SharedBridge = Arc<Bridge>;
+
+impl Bridge {
+    fn new(limit: usize, player: String, token: Option<String>) -> Self {
+        Self {
+            messages: Mutex::new(VecDeque::new()),
+            limit,
+            player,
+            token,
+            new_messages: broadcast::channel(64).0,
+        }
+    }
+
+    fn add(&self, msg: ChatMessage) {
+        let mut messages = self.messages.lock().expect("message lock poisoned");
+        if messages.len() >= self.limit {
+            messages.pop_front();
+        }
+        messages.push_back(msg.clone());
+        // nobody listening is fine
+        let _ = self.new_messages.send(msg);
+    }
+
+    /// Shows a posted message the way the game would echo it back into the chat.
+    fn echo(self: &Arc<Self>, chat_type: ChatType, text: &str) {
+        let (chat_type, target, text) = match ChatCommand::parse(text) {
+            Ok(None) => (chat_type, None, text.to_owned()),
+            Ok(Some(ChatCommand::Channel {
+                chat_type,
+                target,
+                text,
+            })) => (chat_type, target, text),
+            Ok(Some(ChatCommand::Reply(_))) => {
+                self.add(message_now(
+                    ChatType::ErrorMessage,
+                    "",
+                    "There is no one to reply to.",
+                ));
+                return;
+            }
+            Err(e) => {
+                self.add(message_now(ChatType::ErrorMessage, "", &e));
+                return;
+            }
+        };
+
+        let Some(target) = target else {
+            self.add(message_now(chat_type, &self.player, &text));
+            return;
+        };
+        if text.is_empty() {
+            self.add(message_now(
+                ChatType::ErrorMessage,
+                "",
+                "Unable to send tell: no message.",
+            ));
+            return;
+        }
+        let recipient = target
+            .to_string()
+            .replace('@', &CROSS_WORLD_ICON.to_string());
+        self.add(message_now(ChatType::TellOutgoing, &recipient, &text));
+
+        let bridge = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            bridge.add(message_now(
+                ChatType::TellIncoming,
+                &recipient,
+                "Got your tell!",
+            ));
+        });
+    }
+}
+
+fn text_response(status: StatusCode, text: String) -> Response {
+    (status, [(header::CONTENT_TYPE, "text/plain")], text).into_response()
+}
+
+async fn get_messages(
+    State(bridge): State<SharedBridge>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Json<Vec<ChatMessage>> {
+    let since = query
+        .get("since")
+        .and_then(|since| chrono::DateTime::parse_from_rfc3339(since).ok());
+    let messages = bridge.messages.lock().expect("message lock poisoned");
+    Json(
+        messages
+            .iter()
+            .filter(|msg| {
+                since.is_none_or(|since| {
+                    chrono::DateTime::parse_from_rfc3339(&msg.timestamp)
+                        .is_ok_and(|timestamp| timestamp > since)
+                })
+            })
+            .cloned()
+            .collect(),
+    )
+}
+
+async fn post_message(State(bridge): State<SharedBridge>, body: String) -> Response {
+    let request = match serde_json::from_str::<NewMessageRequest>(&body) {
+        Ok(request) => request,
+        Err(e) => {
+            return text_response(
+                StatusCode::BAD_REQUEST,
+                format!("Unable to parse message: {e}"),
+            )
+        }
+    };
+    let Some(chat_type) = ChatType::from_input_channel(&request.channel) else {
+        return text_response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Unable to parse message: The JSON value could not be converted to XIVChatBridge.InputChannel. Path: $.type | Value: {}",
+                request.channel
+            ),
+        );
+    };
+
+    bridge.echo(chat_type, &request.text);
+    StatusCode::CREATED.into_response()
+}
+
+async fn stream_messages(
+    State(bridge): State<SharedBridge>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(bridge.new_messages.subscribe()).filter_map(|msg| {
+        msg.ok()
+            .and_then(|msg| Event::default().json_data(msg).ok())
+            .map(Ok)
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn options(allow: &'static str) -> Response {
+    (StatusCode::OK, [(header::ALLOW, allow)]).into_response()
+}
+
+async fn unknown_method() -> Response {
+    text_response(StatusCode::BAD_REQUEST, "Unknown request method".to_owned())
+}
+
+async fn require_token(
+    State(bridge): State<SharedBridge>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(token) = &bridge.token {
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if bearer != Some(token.as_str()) {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+fn router(bridge: SharedBridge, frontend: Option<PathBuf>) -> Router {
+    let api = Router::new()
+        .route(
+            "/messages",
+            get(get_messages)
+                .post(post_message)
+                .options(|| options("OPTIONS, GET, POST"))
+                .fallback(unknown_method),
+        )
+        .route(
+            "/messages/stream",
+            get(stream_messages).fallback(unknown_method),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            bridge.clone(),
+            require_token,
+        ))
+        .with_state(bridge);
+
+    let router = match frontend {
+        Some(dir) => api.fallback_service(ServeDir::new(dir)),
+        None => api,
+    };
+    router.layer(CorsLayer::permissive())
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let bridge = Arc::new(Bridge::new(args.limit, args.player, args.token));
+
+    if args.interval > 0.0 {
+        let bridge = bridge.clone();
+        let interval = Duration::from_secs_f64(args.interval);
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |d| d.as_nanos() as u64);
+        tokio::spawn(async move {
+            let mut generator = Generator::new(seed);
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                bridge.add(generator.next_message());
+            }
+        });
+    }
+
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, args.port));
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .unwrap_or_else(|e| panic!("Unable to listen on {addr}: {e}"));
+    println!("Mock bridge listening on http://{addr}");
+    axum::serve(listener, router(bridge, args.frontend))
+        .await
+        .expect("server failed");
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    use super::{router, Bridge};
+
+    fn bridge(token: Option<&str>) -> Arc<Bridge> {
+        Arc::new(Bridge::new(
+            10,
+            "Mock Player".to_owned(),
+            token.map(str::to_owned),
+        ))
+    }
+
+    async fn call(router: &Router, request: Request<Body>) -> (StatusCode, String) {
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn post(body: &str) -> Request<Body> {
+        Request::post("/messages")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_owned()))
+            .unwrap()
+    }
+
+    fn get(uri: &str) -> Request<Body> {
+        Request::get(uri).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_post_echo() {
+        let router = router(bridge(None), None);
+
+        let (status, _) = call(&router, post(r#"{"type":"linkshell3","text":"hello"}"#)).await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let (status, body) = call(&router, get("/messages")).await;
+        assert_eq!(status, StatusCode::OK);
+        let messages: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(messages[0]["type"], "ls3");
+        assert_eq!(messages[0]["senderName"], "Mock Player");
+        assert_eq!(messages[0]["text"], "hello");
+
+        // commands switch the channel like in the game
+        for text in ["/p ready", "/tell Alpha Beta@Phoenix hi", "/nope"] {
+            let body = format!(r#"{{"type":"say","text":"{text}"}}"#);
+            let (status, _) = call(&router, post(&body)).await;
+            assert_eq!(status, StatusCode::CREATED);
+        }
+        let (_, body) = call(&router, get("/messages")).await;
+        let messages: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(messages[1]["type"], "party");
+        assert_eq!(messages[1]["text"], "ready");
+        assert_eq!(messages[2]["type"], "tellOutgoing");
+        assert_eq!(messages[2]["senderName"], "Alpha Beta\u{e05d}Phoenix");
+        assert_eq!(messages[3]["type"], "errorMessage");
+    }
+
+    #[tokio::test]
+    async fn test_post_invalid() {
+        let router = router(bridge(None), None);
+
+        let (status, body) = call(&router, post(r#"{"type":"nope","text":"hello"}"#)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.starts_with("Unable to parse message"));
+
+        let (status, body) = call(&router, post("not json")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.starts_with("Unable to parse message"));
+
+        let request = Request::put("/messages").body(Body::empty()).unwrap();
+        let (status, body) = call(&router, request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Unknown request method");
+    }
+
+    #[tokio::test]
+    async fn test_since() {
+        let bridge = bridge(None);
+        let router = router(bridge.clone(), None);
+        for text in ["first", "second"] {
+            bridge.add(super::message_now(super::ChatType::Say, "Alpha Beta", text));
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+
+        let (_, body) = call(&router, get("/messages")).await;
+        let messages: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let since = messages[0]["timestamp"]
+            .as_str()
+            .unwrap()
+            .replace('+', "%2B");
+
+        let (_, body) = call(&router, get(&format!("/messages?since={since}"))).await;
+        let messages: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(messages.as_array().unwrap().len(), 1);
+        assert_eq!(messages[0]["text"], "second");
+    }
+
+    #[tokio::test]
+    async fn test_token() {
+        let router = router(bridge(Some("secret")), None);
+
+        let (status, _) = call(&router, get("/messages")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let request = Request::get("/messages")
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let (status, _) = call(&router, request).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // tokens in URLs end up in logs
+        let (status, _) = call(&router, get("/messages?access_token=secret")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
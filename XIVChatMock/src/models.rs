@@ -0,0 +1,37 @@
+//! Wire types as read by the plugin. Messages are `xivchat_protocol::ChatMessage`, which
+//! serializes like the plugin (`System.Text.Json` with camelCase names and
+//! `JsonStringEnumConverter`).
+
+use serde::Deserialize;
+use xivchat_protocol::{ChatMessage, ChatType};
+
+/// The plugin's `NewMessageRequest`, kept raw to answer unknown channels like the plugin.
+#[derive(Debug, Deserialize)]
+pub struct NewMessageRequest {
+    #[serde(rename = "type")]
+    pub channel: String,
+    pub text: String,
+}
+
+/// A message received just now.
+pub fn message_now(chat_type: ChatType, sender_name: &str, text: &str) -> ChatMessage {
+    ChatMessage {
+        // DateTime.Now as serialized by System.Text.Json
+        timestamp: {
+            // chrono has no 7 digit fraction, .NET ticks are 100ns
+            let now = chrono::Local::now();
+            format!(
+                "{}.{:07}{}",
+                now.format("%Y-%m-%dT%H:%M:%S"),
+                now.timestamp_subsec_nanos() / 100,
+                now.format("%:z")
+            )
+        },
+        chat_type,
+        sender_name: sender_name.to_owned(),
+        text: text.to_owned(),
+        payloads: None,
+        server_id: None,
+        source: None,
+    }
+}
@@ -0,0 +1,143 @@
+//! Synthetic chat traffic covering every chat type.
+
+use xivchat_protocol::{ChatMessage, ChatType, Payload, CROSS_WORLD_ICON};
+
+use crate::models::message_now;
+
+const NAMES: &[&str] = &[
+    "Alpha Beta",
+    "Y'shtola Rhul",
+    "Thancred Waters",
+    "Urianger Augurelt",
+    "Alisaie Leveilleur",
+    "Krile Baldesion",
+];
+const WORLDS: &[&str] = &["Twintania", "Phoenix", "Lich", "Odin", "Shiva"];
+const LINES: &[&str] = &[
+    "Anyone up for Savage tonight?",
+    "o/",
+    "brb, making tea",
+    "Thanks for the run!",
+    "LF1M healer for roulettes",
+    "Does anyone have a spare Grade 8 tincture?",
+    "Meet at the aetheryte in five",
+    "gg",
+    "Is the FC house open for the event on Saturday?",
+    "Who wants to do maps later?",
+];
+const SYSTEM_LINES: &[&str] = &[
+    "You have entered a sanctuary.",
+    "The Duty Finder is now available.",
+    "Your retainer sold a Grade 8 Tincture of Strength for 12,000 gil.",
+    "You obtain 5 Allagan tomestones of poetics.",
+];
+
+/// Chat types that are never sent by a player.
+const SYSTEM_TYPES: &[ChatType] = &[
+    ChatType::None,
+    ChatType::Debug,
+    ChatType::Urgent,
+    ChatType::Notice,
+    ChatType::Echo,
+    ChatType::SystemError,
+    ChatType::SystemMessage,
+    ChatType::GatheringSystemMessage,
+    ChatType::ErrorMessage,
+    ChatType::RetainerSale,
+];
+
+pub struct Generator {
+    state: u64,
+    next_type: usize,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed | 1,
+            next_type: 0,
+        }
+    }
+
+    /// xorshift64, good enough for chatter
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn pick<T: Copy>(&mut self, values: &[T]) -> T {
+        values[(self.next() % values.len() as u64) as usize]
+    }
+
+    pub fn player(&mut self) -> String {
+        let name = self.pick(NAMES);
+        if self.next().is_multiple_of(3) {
+            format!("{name}{CROSS_WORLD_ICON}{}", self.pick(WORLDS))
+        } else {
+            name.to_owned()
+        }
+    }
+
+    /// Creates the next message, going through all chat types in turn.
+    pub fn next_message(&mut self) -> ChatMessage {
+        let chat_type = ChatType::ALL[self.next_type].clone();
+        self.next_type = (self.next_type + 1) % ChatType::ALL.len();
+
+        if SYSTEM_TYPES.contains(&chat_type) {
+            return message_now(chat_type, "", self.pick(SYSTEM_LINES));
+        }
+
+        let sender = self.player();
+        match self.next() % 5 {
+            0 => {
+                let (x, y) = (
+                    (self.next() % 400) as f32 / 10.0,
+                    (self.next() % 400) as f32 / 10.0,
+                );
+                let mut msg = message_now(
+                    chat_type,
+                    &sender,
+                    &format!("Meet me at \u{e0bb}Limsa Lominsa Lower Decks ( {x:.1} , {y:.1} )"),
+                );
+                msg.payloads = Some(vec![
+                    Payload::Text {
+                        text: "Meet me at ".to_owned(),
+                    },
+                    Payload::MapLink {
+                        place_name: "Limsa Lominsa Lower Decks".to_owned(),
+                        x,
+                        y,
+                    },
+                ]);
+                msg
+            }
+            1 => {
+                let mut msg = message_now(
+                    chat_type,
+                    &sender,
+                    "Selling [Grade 8 Tincture of Strength\u{e03c}] cheap",
+                );
+                msg.payloads = Some(vec![
+                    Payload::Text {
+                        text: "Selling ".to_owned(),
+                    },
+                    Payload::Item {
+                        id: 39727,
+                        name: "Grade 8 Tincture of Strength".to_owned(),
+                        hq: true,
+                    },
+                    Payload::Text {
+                        text: " cheap".to_owned(),
+                    },
+                ]);
+                msg
+            }
+            _ => {
+                let line = self.pick(LINES);
+                message_now(chat_type, &sender, line)
+            }
+        }
+    }
+}
@@ -0,0 +1,1085 @@
+//! Messages as exchanged with the XIVChatBridge plugin. The plugin serializes with
+//! `System.Text.Json` using camelCase property names and `JsonStringEnumConverter`, so the
+//! enum names here mirror Dalamud's `XivChatType` and the plugin's `InputChannel`.
+
+use std::{collections::HashSet, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Private use glyph the game puts between a player's name and their home world.
+pub const CROSS_WORLD_ICON: char = '\u{e05d}';
+/// The game's limit for a line typed into the chat, in UTF-8 bytes.
+pub const MAX_MESSAGE_BYTES: usize = 500;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Hash)]
+#[serde(rename_all = "camelCase")]
+#[repr(u32)]
+pub enum ChatType {
+    None,
+    Debug,
+    Urgent,
+    Notice,
+    Say,
+    Shout,
+    Yell,
+    // syntax: /tell player name@world message
+    TellOutgoing,
+    TellIncoming,
+    Party,
+    FreeCompany,
+    Alliance,
+    CrossParty,
+    PvPTeam,
+    #[serde(rename = "ls1")]
+    LinkShell1,
+    #[serde(rename = "ls2")]
+    LinkShell2,
+    #[serde(rename = "ls3")]
+    LinkShell3,
+    #[serde(rename = "ls4")]
+    LinkShell4,
+    #[serde(rename = "ls5")]
+    LinkShell5,
+    #[serde(rename = "ls6")]
+    LinkShell6,
+    #[serde(rename = "ls7")]
+    LinkShell7,
+    #[serde(rename = "ls8")]
+    LinkShell8,
+    CrossLinkShell1,
+    CrossLinkShell2,
+    CrossLinkShell3,
+    CrossLinkShell4,
+    CrossLinkShell5,
+    CrossLinkShell6,
+    CrossLinkShell7,
+    CrossLinkShell8,
+    NoviceNetwork,
+    CustomEmote,
+    StandardEmote,
+    Echo,
+    SystemError,
+    SystemMessage,
+    ErrorMessage,
+    GatheringSystemMessage,
+    #[serde(rename = "npcDialogue")]
+    NPCDialogue,
+    #[serde(rename = "npcDialogueAnnouncements")]
+    NPCDialogueAnnouncements,
+    RetainerSale,
+    #[serde(untagged)]
+    Unimplemented(String),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ChatMessage {
+    pub timestamp: String,
+    #[serde(rename = "type")]
+    pub chat_type: ChatType,
+    #[serde(rename = "senderName")]
+    pub sender_name: String,
+    /// Flattened text of the message, always present.
+    pub text: String,
+    /// Structured form of `text`, for bridges that send the SeString payloads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payloads: Option<Vec<Payload>>,
+    /// Identifier assigned by the bridge, if it sends one. Use `id` to tell messages apart.
+    #[serde(
+        rename = "id",
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_server_id"
+    )]
+    pub server_id: Option<String>,
+    /// Name of the bridge the message was received from, set by clients connected to more
+    /// than one. Never sent by the plugin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// Identity of a message, stable across fetches and page reloads.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MessageId {
+    /// Assigned by the bridge, prefixed with the source if there is one.
+    Server(String),
+    /// Hash of timestamp, type, sender, text and source, for bridges that do not assign ids.
+    Content(u64),
+}
+
+impl Display for MessageId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageId::Server(id) => write!(f, "id-{id}"),
+            MessageId::Content(hash) => write!(f, "msg-{hash:016x}"),
+        }
+    }
+}
+
+/// Accepts numeric ids as well as strings.
+fn deserialize_server_id<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(u64),
+        Text(String),
+    }
+    Ok(Option::<Id>::deserialize(deserializer)?.map(|id| match id {
+        Id::Number(number) => number.to_string(),
+        Id::Text(text) => text,
+    }))
+}
+
+/// Part of a message as the game's SeString payloads describe it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Payload {
+    Text {
+        text: String,
+    },
+    Item {
+        id: u32,
+        name: String,
+        #[serde(default)]
+        hq: bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    MapLink {
+        place_name: String,
+        x: f32,
+        y: f32,
+    },
+    Player {
+        name: String,
+        world: Option<String>,
+    },
+    AutoTranslate {
+        text: String,
+    },
+    Icon {
+        id: u32,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Recipient of a tell in the game's `First Last@World` syntax. The world may be omitted
+/// for players on the same home world.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TellTarget {
+    pub name: String,
+    pub world: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(into = "PluginMessageRequest", try_from = "PluginMessageRequest")]
+pub struct NewMessageRequest {
+    pub chat_type: ChatType,
+    pub text: String,
+    /// Only set for `ChatType::TellOutgoing`.
+    pub target: Option<TellTarget>,
+}
+
+/// The shape the plugin deserializes: `type` is one of its `InputChannel` names. Tells are
+/// sent as `/tell` commands, which the plugin passes through to the game unchanged.
+#[derive(Deserialize, Serialize)]
+struct PluginMessageRequest {
+    #[serde(rename = "type")]
+    channel: String,
+    text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+}
+
+impl From<NewMessageRequest> for PluginMessageRequest {
+    fn from(value: NewMessageRequest) -> Self {
+        let channel = value
+            .chat_type
+            .input_channel()
+            .unwrap_or_default()
+            .to_owned();
+        match value.target {
+            Some(target) => Self {
+                channel,
+                text: format!("/tell {target} {}", value.text),
+                target: Some(target.to_string()),
+            },
+            None => Self {
+                channel,
+                text: value.text,
+                target: None,
+            },
+        }
+    }
+}
+
+/// A chat command at the start of a line typed into the compose box, as understood by the
+/// game. Without text the game switches to the channel instead of sending.
+#[derive(Debug, PartialEq)]
+pub enum ChatCommand {
+    /// `/p text`, `/t First Last@World text` and the like.
+    Channel {
+        chat_type: ChatType,
+        target: Option<TellTarget>,
+        text: String,
+    },
+    /// `/r text`, a tell to whoever sent the last one.
+    Reply(String),
+}
+
+const LINKSHELLS: [ChatType; 8] = [
+    ChatType::LinkShell1,
+    ChatType::LinkShell2,
+    ChatType::LinkShell3,
+    ChatType::LinkShell4,
+    ChatType::LinkShell5,
+    ChatType::LinkShell6,
+    ChatType::LinkShell7,
+    ChatType::LinkShell8,
+];
+
+const CROSS_LINKSHELLS: [ChatType; 8] = [
+    ChatType::CrossLinkShell1,
+    ChatType::CrossLinkShell2,
+    ChatType::CrossLinkShell3,
+    ChatType::CrossLinkShell4,
+    ChatType::CrossLinkShell5,
+    ChatType::CrossLinkShell6,
+    ChatType::CrossLinkShell7,
+    ChatType::CrossLinkShell8,
+];
+
+impl ChatCommand {
+    /// Parses the command `line` starts with, `None` for plain text. The prefixes are the
+    /// plugin's `CommandPrefix` set and the game's short forms.
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let Some(line) = line.trim_start().strip_prefix('/') else {
+            return Ok(None);
+        };
+        let (command, text) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let text = text.trim();
+        let command = command.to_ascii_lowercase();
+        let chat_type = match command.as_str() {
+            "r" | "reply" => return Ok(Some(Self::Reply(text.to_owned()))),
+            "t" | "tell" => return Self::tell(text).map(Some),
+            "s" | "say" => ChatType::Say,
+            "sh" | "shout" => ChatType::Shout,
+            "y" | "yell" => ChatType::Yell,
+            "p" | "party" => ChatType::Party,
+            "a" | "alliance" => ChatType::Alliance,
+            "fc" | "freecompany" => ChatType::FreeCompany,
+            "pvp" | "pvpteam" => ChatType::PvPTeam,
+            "n" | "nn" | "beginner" | "novice" => ChatType::NoviceNetwork,
+            other => Self::linkshell(other).ok_or_else(|| format!("Unknown command /{command}"))?,
+        };
+        Ok(Some(Self::Channel {
+            chat_type,
+            target: None,
+            text: text.to_owned(),
+        }))
+    }
+
+    /// `First Last@World text`, the world is optional like in the game.
+    fn tell(text: &str) -> Result<Self, String> {
+        let mut words = text.splitn(3, char::is_whitespace);
+        let (Some(first), Some(last)) = (words.next(), words.next()) else {
+            return Err("Usage: /t First Last@World message".to_owned());
+        };
+        Ok(Self::Channel {
+            chat_type: ChatType::TellOutgoing,
+            target: Some(format!("{first} {last}").parse()?),
+            text: words.next().unwrap_or_default().trim().to_owned(),
+        })
+    }
+
+    /// `/ls1` to `/ls8` and `/cwls1` to `/cwls8`, with the game's variants.
+    fn linkshell(command: &str) -> Option<ChatType> {
+        let (channels, number) = ["cwlinkshell", "cwls", "cwl"]
+            .iter()
+            .find_map(|prefix| command.strip_prefix(prefix))
+            .map(|number| (&CROSS_LINKSHELLS, number))
+            .or_else(|| {
+                ["linkshell", "ls", "l"]
+                    .iter()
+                    .find_map(|prefix| command.strip_prefix(prefix))
+                    .map(|number| (&LINKSHELLS, number))
+            })?;
+        let index = number.parse::<usize>().ok()?.checked_sub(1)?;
+        channels.get(index).cloned()
+    }
+}
+
+impl NewMessageRequest {
+    /// The bytes the game counts against `MAX_MESSAGE_BYTES`, including the `/tell` command.
+    pub fn game_bytes(&self) -> usize {
+        self.command_bytes() + self.text.len()
+    }
+
+    fn command_bytes(&self) -> usize {
+        self.target
+            .as_ref()
+            .map_or(0, |target| format!("/tell {target} ").len())
+    }
+
+    /// Splits the message into ones that fit the game's limit. With `numbered` every part ends
+    /// with its position, e.g. `(1/3)`.
+    pub fn split(&self, numbered: bool) -> Vec<NewMessageRequest> {
+        let max_bytes = MAX_MESSAGE_BYTES.saturating_sub(self.command_bytes());
+        split_text(&self.text, max_bytes, numbered)
+            .into_iter()
+            .map(|text| Self {
+                chat_type: self.chat_type.clone(),
+                text,
+                target: self.target.clone(),
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<PluginMessageRequest> for NewMessageRequest {
+    type Error = String;
+
+    fn try_from(value: PluginMessageRequest) -> Result<Self, Self::Error> {
+        let chat_type = ChatType::from_input_channel(&value.channel)
+            .ok_or_else(|| format!("Unknown input channel \"{}\"", value.channel))?;
+        if chat_type != ChatType::TellOutgoing {
+            return Ok(Self {
+                chat_type,
+                text: value.text,
+                target: None,
+            });
+        }
+
+        // `/tell First Last@World text`, anything else is passed through as is
+        let Some(command) = value.text.strip_prefix("/tell ") else {
+            return Ok(Self {
+                chat_type,
+                text: value.text,
+                target: None,
+            });
+        };
+        let mut parts = command.splitn(3, ' ');
+        let (Some(first), Some(last), Some(text)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(format!("Invalid tell \"{}\"", value.text));
+        };
+        Ok(Self {
+            chat_type,
+            text: text.to_owned(),
+            target: Some(format!("{first} {last}").parse()?),
+        })
+    }
+}
+
+/// Parses the channel labels of the frontend's channel select, e.g. `FC` or `LS1`.
+impl TryFrom<String> for ChatType {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Ok(match value.as_str() {
+            "Say" => ChatType::Say,
+            "Shout" => ChatType::Shout,
+            "Yell" => ChatType::Yell,
+            "Party" => ChatType::Party,
+            "Alliance" => ChatType::Alliance,
+            "FC" => ChatType::FreeCompany,
+            "Tell" => ChatType::TellOutgoing,
+            "PvP" => ChatType::PvPTeam,
+            "NN" => ChatType::NoviceNetwork,
+            "LS1" => ChatType::LinkShell1,
+            "LS2" => ChatType::LinkShell2,
+            "LS3" => ChatType::LinkShell3,
+            "LS4" => ChatType::LinkShell4,
+            "LS5" => ChatType::LinkShell5,
+            "LS6" => ChatType::LinkShell6,
+            "LS7" => ChatType::LinkShell7,
+            "LS8" => ChatType::LinkShell8,
+            "CWLS1" => ChatType::CrossLinkShell1,
+            "CWLS2" => ChatType::CrossLinkShell2,
+            "CWLS3" => ChatType::CrossLinkShell3,
+            "CWLS4" => ChatType::CrossLinkShell4,
+            "CWLS5" => ChatType::CrossLinkShell5,
+            "CWLS6" => ChatType::CrossLinkShell6,
+            "CWLS7" => ChatType::CrossLinkShell7,
+            "CWLS8" => ChatType::CrossLinkShell8,
+            _ => return Err("Unsupported chatType".to_owned()),
+        })
+    }
+}
+
+impl FromStr for TellTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, world) = match s.trim().split_once('@') {
+            Some((name, world)) => (name, Some(world)),
+            None => (s.trim(), None),
+        };
+
+        // forename and surname, 2-15 characters each and at most 20 combined
+        let parts: Vec<&str> = name.split(' ').collect();
+        let valid_part = |part: &&str| {
+            (2..=15).contains(&part.chars().count())
+                && part
+                    .chars()
+                    .all(|c| c.is_alphabetic() || c == '\'' || c == '-')
+        };
+        if parts.len() != 2 || !parts.iter().all(valid_part) || name.chars().count() > 21 {
+            return Err(format!(
+                "Invalid player name \"{name}\", expected First Last@World"
+            ));
+        }
+
+        let world = match world {
+            Some(world) if world.is_empty() || !world.chars().all(|c| c.is_ascii_alphabetic()) => {
+                return Err(format!("Invalid world \"{world}\""))
+            }
+            world => world.map(str::to_owned),
+        };
+
+        Ok(Self {
+            name: name.to_owned(),
+            world,
+        })
+    }
+}
+
+impl Display for TellTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.world {
+            Some(world) => write!(f, "{}@{world}", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl TellTarget {
+    /// Parses the sender of an incoming tell, which is either `First Last` or
+    /// `First Last` followed by the cross world icon and the home world.
+    pub fn from_sender(sender: &str) -> Option<Self> {
+        let target = match sender.split_once(CROSS_WORLD_ICON) {
+            Some((name, world)) => format!("{name}@{world}"),
+            None => sender.to_owned(),
+        };
+        target.parse().ok()
+    }
+}
+
+impl Display for ChatType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match &self {
+            ChatType::None => "",
+            ChatType::Debug => "DBG",
+            ChatType::Urgent => "Urgent",
+            ChatType::Notice => "Notice",
+            ChatType::Say => "Say",
+            ChatType::Shout => "Shout",
+            ChatType::Yell => "Yell",
+            ChatType::TellOutgoing => "Tell>",
+            ChatType::TellIncoming => "Tell<",
+            ChatType::Party => "Party",
+            ChatType::FreeCompany => "FC",
+            ChatType::Alliance => "Alliance",
+            ChatType::CrossParty => "Party",
+            ChatType::PvPTeam => "PvP",
+            ChatType::LinkShell1 => "LS1",
+            ChatType::LinkShell2 => "LS2",
+            ChatType::LinkShell3 => "LS3",
+            ChatType::LinkShell4 => "LS4",
+            ChatType::LinkShell5 => "LS5",
+            ChatType::LinkShell6 => "LS6",
+            ChatType::LinkShell7 => "LS7",
+            ChatType::LinkShell8 => "LS8",
+            ChatType::CrossLinkShell1 => "CWLS1",
+            ChatType::CrossLinkShell2 => "CWLS2",
+            ChatType::CrossLinkShell3 => "CWLS3",
+            ChatType::CrossLinkShell4 => "CWLS4",
+            ChatType::CrossLinkShell5 => "CWLS5",
+            ChatType::CrossLinkShell6 => "CWLS6",
+            ChatType::CrossLinkShell7 => "CWLS7",
+            ChatType::CrossLinkShell8 => "CWLS8",
+            ChatType::NoviceNetwork => "NN",
+            ChatType::CustomEmote => "CEmote",
+            ChatType::StandardEmote => "Emote",
+            ChatType::Echo => "Echo",
+            ChatType::SystemError => "Error(System)",
+            ChatType::SystemMessage => "System",
+            ChatType::ErrorMessage => "Error",
+            ChatType::GatheringSystemMessage => "Gathering",
+            ChatType::NPCDialogue | ChatType::NPCDialogueAnnouncements => "NPC",
+            ChatType::RetainerSale => "Retainer",
+            ChatType::Unimplemented(val) => {
+                log::warn!("Unknown chatType: {val}");
+                "Unknown"
+            }
+        };
+        write!(f, "{str}",)
+    }
+}
+
+impl ChatType {
+    /// Every type of Dalamud's `XivChatType`, in its order.
+    pub const ALL: [ChatType; 41] = [
+        Self::None,
+        Self::Debug,
+        Self::Urgent,
+        Self::Notice,
+        Self::Say,
+        Self::Shout,
+        Self::TellOutgoing,
+        Self::TellIncoming,
+        Self::Party,
+        Self::Alliance,
+        Self::LinkShell1,
+        Self::LinkShell2,
+        Self::LinkShell3,
+        Self::LinkShell4,
+        Self::LinkShell5,
+        Self::LinkShell6,
+        Self::LinkShell7,
+        Self::LinkShell8,
+        Self::FreeCompany,
+        Self::NoviceNetwork,
+        Self::CustomEmote,
+        Self::StandardEmote,
+        Self::Yell,
+        Self::CrossParty,
+        Self::PvPTeam,
+        Self::CrossLinkShell1,
+        Self::Echo,
+        Self::SystemError,
+        Self::SystemMessage,
+        Self::GatheringSystemMessage,
+        Self::ErrorMessage,
+        Self::NPCDialogue,
+        Self::NPCDialogueAnnouncements,
+        Self::RetainerSale,
+        Self::CrossLinkShell2,
+        Self::CrossLinkShell3,
+        Self::CrossLinkShell4,
+        Self::CrossLinkShell5,
+        Self::CrossLinkShell6,
+        Self::CrossLinkShell7,
+        Self::CrossLinkShell8,
+    ];
+
+    /// All types messages can be sent as, in the order of the plugin's `InputChannel`.
+    pub const SENDABLE: [ChatType; 25] = [
+        Self::TellOutgoing,
+        Self::Say,
+        Self::Party,
+        Self::Alliance,
+        Self::Yell,
+        Self::Shout,
+        Self::FreeCompany,
+        Self::PvPTeam,
+        Self::NoviceNetwork,
+        Self::CrossLinkShell1,
+        Self::CrossLinkShell2,
+        Self::CrossLinkShell3,
+        Self::CrossLinkShell4,
+        Self::CrossLinkShell5,
+        Self::CrossLinkShell6,
+        Self::CrossLinkShell7,
+        Self::CrossLinkShell8,
+        Self::LinkShell1,
+        Self::LinkShell2,
+        Self::LinkShell3,
+        Self::LinkShell4,
+        Self::LinkShell5,
+        Self::LinkShell6,
+        Self::LinkShell7,
+        Self::LinkShell8,
+    ];
+
+    /// Looks up an `InputChannel` name, ignoring case like `JsonStringEnumConverter` does.
+    pub fn from_input_channel(name: &str) -> Option<Self> {
+        Self::SENDABLE.into_iter().find(|chat_type| {
+            chat_type
+                .input_channel()
+                .is_some_and(|channel| channel.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// Name of the plugin's `InputChannel` used to send messages of this type.
+    pub fn input_channel(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::Say => "say",
+            Self::Shout => "shout",
+            Self::Yell => "yell",
+            Self::TellOutgoing => "tell",
+            Self::Party => "party",
+            Self::Alliance => "alliance",
+            Self::FreeCompany => "freeCompany",
+            Self::PvPTeam => "pvpTeam",
+            Self::NoviceNetwork => "noviceNetwork",
+            Self::LinkShell1 => "linkshell1",
+            Self::LinkShell2 => "linkshell2",
+            Self::LinkShell3 => "linkshell3",
+            Self::LinkShell4 => "linkshell4",
+            Self::LinkShell5 => "linkshell5",
+            Self::LinkShell6 => "linkshell6",
+            Self::LinkShell7 => "linkshell7",
+            Self::LinkShell8 => "linkshell8",
+            Self::CrossLinkShell1 => "crossLinkshell1",
+            Self::CrossLinkShell2 => "crossLinkshell2",
+            Self::CrossLinkShell3 => "crossLinkshell3",
+            Self::CrossLinkShell4 => "crossLinkshell4",
+            Self::CrossLinkShell5 => "crossLinkshell5",
+            Self::CrossLinkShell6 => "crossLinkshell6",
+            Self::CrossLinkShell7 => "crossLinkshell7",
+            Self::CrossLinkShell8 => "crossLinkshell8",
+            _ => return None,
+        })
+    }
+
+    pub fn get_color(&self) -> String {
+        match self {
+            Self::Yell => "#fcfc03",
+            Self::Shout => "#ffce63",
+            Self::TellIncoming | Self::TellOutgoing => "#f263ff",
+            Self::Alliance => "#ed9511",
+            Self::FreeCompany => "#4ef542",
+            Self::Party => "#426ff5",
+            cwl if (Self::CrossLinkShell1..=Self::CrossLinkShell8).contains(cwl) => "#9f3cbd",
+            Self::NoviceNetwork => "#cfe05c",
+            ls if (Self::LinkShell1..=Self::LinkShell8).contains(ls) => "#fad2b9",
+            Self::StandardEmote | Self::CustomEmote => "#e1faf9",
+            Self::NPCDialogue | Self::NPCDialogueAnnouncements => "#6ead10",
+            _ => "#FFFFFFFF",
+        }
+        .to_owned()
+    }
+}
+
+impl ChatMessage {
+    pub fn parsed_timestamp(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.timestamp.parse::<chrono::DateTime<chrono::Utc>>().ok()
+    }
+
+    pub fn id(&self) -> MessageId {
+        if let Some(id) = &self.server_id {
+            return MessageId::Server(match &self.source {
+                Some(source) => format!("{source}/{id}"),
+                None => id.clone(),
+            });
+        }
+        // FNV-1a, the hash ends up in stored history and has to stay the same across builds
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let chat_type = format!("{:?}", self.chat_type);
+        let fields = [&self.timestamp, &chat_type, &self.sender_name, &self.text];
+        for field in fields.into_iter().chain(&self.source) {
+            for byte in field.bytes().chain([0]) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        MessageId::Content(hash)
+    }
+
+    pub fn formatted_timestamp(&self) -> String {
+        if let Some(date_time) = self.parsed_timestamp() {
+            chrono::DateTime::<chrono::Local>::from(date_time)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        } else {
+            "N/A".to_owned()
+        }
+    }
+}
+
+/// Merges the result of a fetch made with the `since` cursor into `messages`.
+///
+/// Servers that do not know the cursor answer with their whole backlog. This is detected
+/// by the presence of messages not newer than the cursor, in which case the backlog is
+/// merged with `union_messages` instead, keeping older messages the server no longer has.
+/// Returns the number of new messages at the end of `messages`.
+pub fn merge_messages(
+    messages: &mut Vec<ChatMessage>,
+    fetched: Vec<ChatMessage>,
+    since: Option<&str>,
+) -> usize {
+    let since = since.and_then(|s| s.parse::<chrono::DateTime<chrono::Utc>>().ok());
+    let is_newer = |msg: &ChatMessage| {
+        since.is_some_and(|since| msg.parsed_timestamp().is_some_and(|ts| ts > since))
+    };
+    if since.is_none() || !fetched.iter().all(is_newer) {
+        return union_messages(messages, fetched);
+    }
+
+    // another fetch may have completed in the meantime
+    append_newer(messages, fetched)
+}
+
+/// Appends the messages of `fetched` that are newer than the last one in `messages`.
+pub fn append_newer(messages: &mut Vec<ChatMessage>, fetched: Vec<ChatMessage>) -> usize {
+    let newest = messages.last().and_then(ChatMessage::parsed_timestamp);
+    let before = messages.len();
+    messages.extend(fetched.into_iter().filter(|msg| {
+        newest.is_none_or(|newest| msg.parsed_timestamp().is_some_and(|ts| ts > newest))
+    }));
+    messages.len() - before
+}
+
+/// Adds the messages of `fetched` that are missing from `messages`, keeping it ordered by
+/// timestamp. Returns the number of added messages newer than the previously newest one,
+/// which end up at the end of `messages`.
+pub fn union_messages(messages: &mut Vec<ChatMessage>, fetched: Vec<ChatMessage>) -> usize {
+    let newest = messages.last().and_then(ChatMessage::parsed_timestamp);
+    insert_messages(messages, fetched)
+        .iter()
+        .filter(|msg| {
+            newest.is_none_or(|newest| msg.parsed_timestamp().is_some_and(|ts| ts > newest))
+        })
+        .count()
+}
+
+/// Adds the messages of `fetched` that are missing from `messages`, keeping it ordered by
+/// timestamp. Returns the added messages.
+pub fn insert_messages(
+    messages: &mut Vec<ChatMessage>,
+    fetched: Vec<ChatMessage>,
+) -> Vec<ChatMessage> {
+    let newest = messages.last().and_then(ChatMessage::parsed_timestamp);
+    let mut known: HashSet<MessageId> = messages.iter().map(ChatMessage::id).collect();
+    let missing: Vec<ChatMessage> = fetched
+        .into_iter()
+        .filter(|msg| known.insert(msg.id()))
+        .collect();
+
+    let in_order = missing.iter().all(|msg| {
+        newest.is_none_or(|newest| msg.parsed_timestamp().is_some_and(|ts| ts >= newest))
+    });
+    messages.extend(missing.iter().cloned());
+    if !in_order {
+        // stable, so messages of the same tick keep their order
+        messages.sort_by_key(ChatMessage::parsed_timestamp);
+    }
+    missing
+}
+
+/// Splits `text` into parts of at most `max_bytes`, at whitespace where possible. Numbers
+/// count towards the limit. Text that fits is returned as is, without a number.
+pub fn split_text(text: &str, max_bytes: usize, numbered: bool) -> Vec<String> {
+    let text = text.trim();
+    if text.len() <= max_bytes {
+        return vec![text.to_owned()];
+    }
+    if !numbered {
+        return split_words(text, max_bytes);
+    }
+    // the space taken by the numbers depends on how many parts there are
+    let mut digits = 1;
+    loop {
+        let parts = split_words(text, max_bytes.saturating_sub(" (/)".len() + 2 * digits));
+        let total = parts.len();
+        if total.to_string().len() <= digits {
+            return parts
+                .into_iter()
+                .enumerate()
+                .map(|(index, part)| format!("{part} ({}/{total})", index + 1))
+                .collect();
+        }
+        digits = total.to_string().len();
+    }
+}
+
+fn split_words(text: &str, max_bytes: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while rest.len() > max_bytes {
+        let mut cut = max_bytes;
+        while !rest.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        if cut == 0 {
+            // not even a single character fits, take one anyway
+            cut = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        let at_word_end = rest[cut..].starts_with(char::is_whitespace);
+        let (part, next) = match rest[..cut].rfind(char::is_whitespace) {
+            Some(space) if !at_word_end && space > 0 => (&rest[..space], &rest[space..]),
+            // a single word longer than the limit is broken anywhere
+            _ => (&rest[..cut], &rest[cut..]),
+        };
+        parts.push(part.trim_end().to_owned());
+        rest = next.trim_start();
+    }
+    if !rest.is_empty() {
+        parts.push(rest.to_owned());
+    }
+    parts
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        merge_messages, split_text, ChatCommand, ChatMessage, ChatType, MessageId,
+        NewMessageRequest, Payload, TellTarget, MAX_MESSAGE_BYTES,
+    };
+
+    const TIMESTAMP: &str = "2024-07-14T10:18:02.8379913+02:00";
+
+    fn message(timestamp: &str, text: &str) -> ChatMessage {
+        ChatMessage {
+            timestamp: timestamp.to_owned(),
+            chat_type: ChatType::Say,
+            sender_name: "none".to_owned(),
+            text: text.to_owned(),
+            payloads: None,
+            server_id: None,
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let message = message(TIMESTAMP, "test");
+
+        // 08:18 UTC, shown in the local time zone
+        let expected = chrono::DateTime::parse_from_rfc3339(TIMESTAMP)
+            .unwrap()
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string();
+        assert_eq!(message.formatted_timestamp(), expected);
+    }
+
+    #[test]
+    fn test_merge_with_cursor() {
+        let mut messages = vec![message(TIMESTAMP, "first")];
+        let fetched = vec![message("2024-07-14T10:18:03.0000000+02:00", "second")];
+
+        let new = merge_messages(&mut messages, fetched, Some(TIMESTAMP));
+        assert_eq!(new, 1);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].text, "second");
+    }
+
+    #[test]
+    fn test_merge_cursor_ignored() {
+        let mut messages = vec![message(TIMESTAMP, "first")];
+        let fetched = vec![
+            message("2024-07-14T10:18:01.0000000+02:00", "older"),
+            message(TIMESTAMP, "first"),
+            message("2024-07-14T10:18:03.0000000+02:00", "second"),
+        ];
+
+        let new = merge_messages(&mut messages, fetched, Some(TIMESTAMP));
+        assert_eq!(new, 1);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].text, "older");
+    }
+
+    #[test]
+    fn test_union() {
+        let mut messages = vec![
+            message(TIMESTAMP, "first"),
+            message("2024-07-14T10:18:04.0000000+02:00", "third"),
+        ];
+        // the server no longer has "first", but a message the history missed
+        let fetched = vec![
+            message("2024-07-14T10:18:03.0000000+02:00", "second"),
+            message("2024-07-14T10:18:04.0000000+02:00", "third"),
+            message("2024-07-14T10:18:05.0000000+02:00", "fourth"),
+        ];
+
+        let new = merge_messages(&mut messages, fetched, None);
+        assert_eq!(new, 1);
+        let texts: Vec<&str> = messages.iter().map(|msg| msg.text.as_str()).collect();
+        assert_eq!(texts, ["first", "second", "third", "fourth"]);
+    }
+
+    #[test]
+    fn test_message_id() {
+        let first = message(TIMESTAMP, "first");
+        let mut second = message(TIMESTAMP, "second");
+        assert_ne!(first.id(), second.id());
+        assert_eq!(first.id(), first.clone().id());
+        second.chat_type = ChatType::Party;
+        assert_ne!(second.id(), message(TIMESTAMP, "second").id());
+
+        let with_id: ChatMessage = serde_json::from_str(
+            r#"{"id":42,"timestamp":"2024-07-14T10:18:02.8379913+02:00","type":"say","senderName":"none","text":"first"}"#,
+        )
+        .unwrap();
+        assert_eq!(with_id.id(), MessageId::Server("42".to_owned()));
+        assert_eq!(with_id.id().to_string(), "id-42");
+
+        // the same message received through two bridges
+        let mut other = first.clone();
+        other.source = Some("Alt".to_owned());
+        assert_ne!(other.id(), first.id());
+
+        // duplicates within a fetch are dropped as well
+        let mut messages = Vec::new();
+        let new = merge_messages(&mut messages, vec![first.clone(), first], None);
+        assert_eq!(new, 1);
+    }
+
+    #[test]
+    fn test_tell_target() {
+        let target: TellTarget = "Alpha O'Beta@Twintania".parse().unwrap();
+        assert_eq!(target.name, "Alpha O'Beta");
+        assert_eq!(target.world.as_deref(), Some("Twintania"));
+
+        assert!("Alpha@Twintania".parse::<TellTarget>().is_err());
+        assert!("Alpha Beta@".parse::<TellTarget>().is_err());
+        assert_eq!(
+            TellTarget::from_sender("Alpha Beta\u{e05d}Phoenix"),
+            Some(TellTarget {
+                name: "Alpha Beta".to_owned(),
+                world: Some("Phoenix".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_tell_request() {
+        let request = NewMessageRequest {
+            chat_type: ChatType::TellOutgoing,
+            text: "hi".to_owned(),
+            target: Some("Alpha Beta@Phoenix".parse().unwrap()),
+        };
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"type":"tell","text":"/tell Alpha Beta@Phoenix hi","target":"Alpha Beta@Phoenix"}"#
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split_text(" short ", 10, true), vec!["short"]);
+        assert_eq!(
+            split_text("one two three four", 9, false),
+            vec!["one two", "three", "four"]
+        );
+        assert_eq!(
+            split_text("abcdefghij", 4, false),
+            vec!["abcd", "efgh", "ij"]
+        );
+        // never in the middle of a character
+        assert_eq!(split_text("ééé", 3, false), vec!["é", "é", "é"]);
+
+        let numbered = split_text("one two three four", 14, true);
+        assert_eq!(numbered, vec!["one two (1/3)", "three (2/3)", "four (3/3)"]);
+
+        // ten parts and more need wider numbers, every part still fits
+        let text = "word ".repeat(70);
+        let parts = split_text(&text, 40, true);
+        assert_eq!(parts.len(), 12);
+        assert!(parts.iter().all(|part| part.len() <= 40));
+        assert!(parts[11].ends_with("word (12/12)"));
+
+        let tell = NewMessageRequest {
+            chat_type: ChatType::TellOutgoing,
+            text: "lorem ipsum ".repeat(50),
+            target: Some("Alpha Beta@Phoenix".parse().unwrap()),
+        };
+        assert_eq!(tell.game_bytes(), 600 + "/tell Alpha Beta@Phoenix ".len());
+        let parts = tell.split(false);
+        assert_eq!(parts.len(), 2);
+        assert!(parts
+            .iter()
+            .all(|part| part.game_bytes() <= MAX_MESSAGE_BYTES && part.target == tell.target));
+    }
+
+    #[test]
+    fn test_chat_command() {
+        let channel = |chat_type, text: &str| {
+            Ok(Some(ChatCommand::Channel {
+                chat_type,
+                target: None,
+                text: text.to_owned(),
+            }))
+        };
+        assert_eq!(ChatCommand::parse("hello /p"), Ok(None));
+        assert_eq!(
+            ChatCommand::parse("/p hello there"),
+            channel(ChatType::Party, "hello there")
+        );
+        assert_eq!(
+            ChatCommand::parse("/FC hi"),
+            channel(ChatType::FreeCompany, "hi")
+        );
+        assert_eq!(
+            ChatCommand::parse("/ls3 yo"),
+            channel(ChatType::LinkShell3, "yo")
+        );
+        assert_eq!(
+            ChatCommand::parse("/cwl2 yo"),
+            channel(ChatType::CrossLinkShell2, "yo")
+        );
+        assert_eq!(
+            ChatCommand::parse("/cwls8 yo"),
+            channel(ChatType::CrossLinkShell8, "yo")
+        );
+        // switches the channel
+        assert_eq!(ChatCommand::parse("/sh"), channel(ChatType::Shout, ""));
+
+        assert_eq!(
+            ChatCommand::parse("/t Alpha Beta@Phoenix hey you"),
+            Ok(Some(ChatCommand::Channel {
+                chat_type: ChatType::TellOutgoing,
+                target: "Alpha Beta@Phoenix".parse().ok(),
+                text: "hey you".to_owned(),
+            }))
+        );
+        assert!(ChatCommand::parse("/t Alpha").is_err());
+        assert_eq!(
+            ChatCommand::parse("/r thanks"),
+            Ok(Some(ChatCommand::Reply("thanks".to_owned())))
+        );
+
+        assert_eq!(
+            ChatCommand::parse("/em waves"),
+            Err("Unknown command /em".to_owned())
+        );
+        assert!(ChatCommand::parse("/ls9 hi").is_err());
+        assert!(ChatCommand::parse("/ls0 hi").is_err());
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let request: NewMessageRequest =
+            serde_json::from_str(r#"{"type":"Tell","text":"/tell Alpha Beta@Phoenix hi there"}"#)
+                .unwrap();
+        assert_eq!(request.chat_type, ChatType::TellOutgoing);
+        assert_eq!(request.text, "hi there");
+        assert_eq!(request.target, "Alpha Beta@Phoenix".parse().ok());
+
+        assert!(serde_json::from_str::<NewMessageRequest>(
+            r#"{"type":"tell","text":"/tell Alpha hi"}"#
+        )
+        .is_err());
+        assert!(
+            serde_json::from_str::<NewMessageRequest>(r#"{"type":"echo","text":"hi"}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_payloads() {
+        let json = r#"{"timestamp":"2024-07-14T10:18:02.8379913+02:00","type":"party","senderName":"Alpha Beta",
+            "text":"meet at Limsa ( 11.2 , 10.5 )","payloads":[
+                {"type":"text","text":"meet at "},
+                {"type":"mapLink","placeName":"Limsa","x":11.2,"y":10.5},
+                {"type":"sound","id":3}]}"#;
+        let msg: ChatMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            msg.payloads.unwrap(),
+            vec![
+                Payload::Text {
+                    text: "meet at ".to_owned()
+                },
+                Payload::MapLink {
+                    place_name: "Limsa".to_owned(),
+                    x: 11.2,
+                    y: 10.5
+                },
+                Payload::Unknown,
+            ]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Pins down how the models read and write the wire format: enum names, field renames,
+//! timestamps and `System.Text.Json` escapes. The fixtures are written by hand from the
+//! serializer settings in `Plugin.cs`, so they only check this crate against those
+//! assumptions, not against what a running plugin sends.
+
+use serde_json::Value;
+use xivchat_protocol::{ChatMessage, ChatType, NewMessageRequest, TellTarget};
+
+/// One message of every `XivChatType`.
+const MESSAGES: &str = include_str!("fixtures/messages.json");
+/// One request for every `InputChannel` in `GameFunctions.cs`.
+const REQUESTS: &str = include_str!("fixtures/requests.json");
+
+#[test]
+fn test_message_types() {
+    let messages: Vec<ChatMessage> = serde_json::from_str(MESSAGES).unwrap();
+    assert_eq!(messages.len(), 41);
+
+    for (i, msg) in messages.iter().enumerate() {
+        assert!(
+            !matches!(msg.chat_type, ChatType::Unimplemented(_)),
+            "{:?} is not mapped",
+            msg.chat_type
+        );
+        assert!(
+            !messages[..i].iter().any(|m| m.chat_type == msg.chat_type),
+            "{:?} is mapped twice",
+            msg.chat_type
+        );
+        assert!(
+            msg.parsed_timestamp().is_some(),
+            "unable to parse {}",
+            msg.timestamp
+        );
+    }
+}
+
+#[test]
+fn test_message_round_trip() {
+    let messages: Vec<ChatMessage> = serde_json::from_str(MESSAGES).unwrap();
+    let expected: Value = serde_json::from_str(MESSAGES).unwrap();
+    assert_eq!(serde_json::to_value(&messages).unwrap(), expected);
+}
+
+#[test]
+fn test_unknown_type() {
+    let json = r#"{"timestamp":"2024-07-14T10:18:02.8379913+02:00","type":"someFutureType","senderName":"","text":"hi"}"#;
+    let msg: ChatMessage = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        msg.chat_type,
+        ChatType::Unimplemented("someFutureType".to_owned())
+    );
+    assert_eq!(
+        serde_json::to_value(&msg).unwrap(),
+        serde_json::from_str::<Value>(json).unwrap()
+    );
+}
+
+#[test]
+fn test_timestamps() {
+    let parse = |timestamp: &str| {
+        let json =
+            format!(r#"{{"timestamp":"{timestamp}","type":"say","senderName":"","text":""}}"#);
+        serde_json::from_str::<ChatMessage>(&json)
+            .unwrap()
+            .parsed_timestamp()
+            .map(|ts| ts.to_rfc3339())
+    };
+
+    // DateTime.Now, DateTime.UtcNow and trimmed trailing zeros
+    assert_eq!(
+        parse("2024-07-14T10:18:02.8379913+02:00").as_deref(),
+        Some("2024-07-14T08:18:02.837991300+00:00")
+    );
+    assert_eq!(
+        parse("2024-07-14T08:18:02.8379913Z").as_deref(),
+        Some("2024-07-14T08:18:02.837991300+00:00")
+    );
+    assert_eq!(
+        parse("2024-07-14T10:18:02.5+02:00").as_deref(),
+        Some("2024-07-14T08:18:02.500+00:00")
+    );
+}
+
+#[test]
+fn test_sender_world() {
+    let messages: Vec<ChatMessage> = serde_json::from_str(MESSAGES).unwrap();
+    let tell = messages
+        .iter()
+        .find(|msg| msg.chat_type == ChatType::TellIncoming)
+        .unwrap();
+    assert_eq!(
+        TellTarget::from_sender(&tell.sender_name).map(|target| target.to_string()),
+        Some("Alpha Beta@Phoenix".to_owned())
+    );
+}
+
+#[test]
+fn test_requests() {
+    let requests: Vec<NewMessageRequest> = serde_json::from_str(REQUESTS).unwrap();
+    let chat_types: Vec<ChatType> = requests.iter().map(|r| r.chat_type.clone()).collect();
+    assert_eq!(chat_types, ChatType::SENDABLE);
+
+    // the plugin only reads `type` and `text`
+    let expected: Vec<Value> = serde_json::from_str(REQUESTS).unwrap();
+    for (request, expected) in requests.iter().zip(expected) {
+        let serialized = serde_json::to_value(request).unwrap();
+        assert_eq!(serialized["type"], expected["type"]);
+        assert_eq!(serialized["text"], expected["text"]);
+    }
+}
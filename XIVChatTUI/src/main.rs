@@ -0,0 +1,299 @@
+//! Terminal client for XIVChatBridge: a scrollable chat log and an input line to send messages.
+
+mod ui;
+
+use std::{io, process::ExitCode, sync::Arc, time::Duration};
+
+use clap::Parser;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use futures::StreamExt;
+use ratatui::DefaultTerminal;
+use tokio::sync::mpsc;
+use xivchat_client::{
+    models::{merge_messages, ChatMessage, ChatType, NewMessageRequest, TellTarget},
+    Client, ErrorKind, ErrorMessage, DEFAULT_URL,
+};
+
+const REFRESH_TIME_SEC: u64 = 3;
+/// Messages scrolled per page, however many lines they take up.
+const PAGE: usize = 10;
+
+/// Channels cycled through with tab, in the order of the frontend's channel select.
+const CHANNELS: [ChatType; 25] = [
+    ChatType::Say,
+    ChatType::Shout,
+    ChatType::Yell,
+    ChatType::Party,
+    ChatType::Alliance,
+    ChatType::FreeCompany,
+    ChatType::TellOutgoing,
+    ChatType::PvPTeam,
+    ChatType::NoviceNetwork,
+    ChatType::LinkShell1,
+    ChatType::LinkShell2,
+    ChatType::LinkShell3,
+    ChatType::LinkShell4,
+    ChatType::LinkShell5,
+    ChatType::LinkShell6,
+    ChatType::LinkShell7,
+    ChatType::LinkShell8,
+    ChatType::CrossLinkShell1,
+    ChatType::CrossLinkShell2,
+    ChatType::CrossLinkShell3,
+    ChatType::CrossLinkShell4,
+    ChatType::CrossLinkShell5,
+    ChatType::CrossLinkShell6,
+    ChatType::CrossLinkShell7,
+    ChatType::CrossLinkShell8,
+];
+
+#[derive(Parser)]
+#[command(about)]
+struct Args {
+    /// Address of the bridge
+    #[arg(long, default_value = DEFAULT_URL)]
+    url: String,
+    /// Access token, if the bridge requires one
+    #[arg(long, env = "XIVCHAT_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+}
+
+/// Results of the requests running in the background.
+enum Response {
+    Fetched {
+        since: Option<String>,
+        result: Result<Vec<ChatMessage>, ErrorMessage>,
+    },
+    Sent(Result<(), ErrorMessage>),
+}
+
+enum Action {
+    Nothing,
+    Send(NewMessageRequest),
+    Quit,
+}
+
+#[derive(Default)]
+pub struct Tui {
+    messages: Vec<ChatMessage>,
+    /// Number of messages hidden below the bottom of the log.
+    scroll: usize,
+    channel: usize,
+    input: String,
+    error: Option<ErrorMessage>,
+    fetching: bool,
+}
+
+impl Tui {
+    fn chat_type(&self) -> &ChatType {
+        &CHANNELS[self.channel]
+    }
+
+    fn handle_response(&mut self, response: Response) {
+        match response {
+            Response::Fetched { since, result } => {
+                self.fetching = false;
+                match result {
+                    Ok(fetched) => {
+                        let new = merge_messages(&mut self.messages, fetched, since.as_deref());
+                        if self.scroll > 0 {
+                            // keep the scrolled to messages in view
+                            self.scroll = (self.scroll + new).min(self.messages.len());
+                        }
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+            Response::Sent(result) => self.error = result.err(),
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Action::Quit,
+            KeyCode::Char('c') if ctrl => return Action::Quit,
+            KeyCode::Char('r') if ctrl => self.reply_to_last_tell(),
+            KeyCode::Char(c) if !ctrl => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Tab => self.channel = (self.channel + 1) % CHANNELS.len(),
+            KeyCode::BackTab => self.channel = (self.channel + CHANNELS.len() - 1) % CHANNELS.len(),
+            KeyCode::Up => self.scroll_by(1),
+            KeyCode::Down => self.scroll_by(-1),
+            KeyCode::PageUp => self.scroll_by(PAGE as isize),
+            KeyCode::PageDown => self.scroll_by(-(PAGE as isize)),
+            KeyCode::End => self.scroll = 0,
+            KeyCode::Enter if !self.input.trim().is_empty() => {
+                match new_request(self.chat_type(), &self.input) {
+                    Ok(request) => {
+                        self.input.clear();
+                        return Action::Send(request);
+                    }
+                    Err(e) => {
+                        self.error = Some(ErrorMessage {
+                            kind: ErrorKind::Input,
+                            description: e,
+                            details: None,
+                        })
+                    }
+                }
+            }
+            _ => {}
+        }
+        Action::Nothing
+    }
+
+    fn scroll_by(&mut self, messages: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(messages)
+            .min(self.messages.len().saturating_sub(1));
+    }
+
+    fn reply_to_last_tell(&mut self) {
+        let last_tell = self
+            .messages
+            .iter()
+            .rev()
+            .find(|msg| msg.chat_type == ChatType::TellIncoming)
+            .and_then(|msg| TellTarget::from_sender(&msg.sender_name));
+        if let Some(target) = last_tell {
+            self.channel = CHANNELS
+                .iter()
+                .position(|chat_type| *chat_type == ChatType::TellOutgoing)
+                .unwrap_or_default();
+            self.input = format!("{target} ");
+        }
+    }
+}
+
+/// Builds the request for the input line. Tells start with the recipient, `First Last@World text`.
+fn new_request(chat_type: &ChatType, input: &str) -> Result<NewMessageRequest, String> {
+    if *chat_type != ChatType::TellOutgoing {
+        return Ok(NewMessageRequest {
+            chat_type: chat_type.clone(),
+            text: input.to_owned(),
+            target: None,
+        });
+    }
+
+    let mut parts = input.trim_start().splitn(3, ' ');
+    let (Some(first), Some(last), Some(text)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err("Tells need a recipient: First Last@World message".to_owned());
+    };
+    Ok(NewMessageRequest {
+        chat_type: chat_type.clone(),
+        text: text.to_owned(),
+        target: Some(format!("{first} {last}").parse()?),
+    })
+}
+
+fn fetch(client: &Arc<Client>, tui: &mut Tui, responses: &mpsc::UnboundedSender<Response>) {
+    if tui.fetching {
+        return;
+    }
+    tui.fetching = true;
+    let since = tui.messages.last().map(|msg| msg.timestamp.clone());
+    let client = client.clone();
+    let responses = responses.clone();
+    tokio::spawn(async move {
+        let result = client.get_messages(since.as_deref()).await;
+        // the receiver is only gone when quitting
+        let _ = responses.send(Response::Fetched { since, result });
+    });
+}
+
+async fn run(terminal: &mut DefaultTerminal, client: Client) -> io::Result<()> {
+    let client = Arc::new(client);
+    let (responses, mut received) = mpsc::unbounded_channel();
+    let mut events = EventStream::new();
+    let mut refresh = tokio::time::interval(Duration::from_secs(REFRESH_TIME_SEC));
+    let mut tui = Tui::default();
+
+    loop {
+        terminal.draw(|frame| ui::draw(frame, &tui))?;
+
+        tokio::select! {
+            _ = refresh.tick() => fetch(&client, &mut tui, &responses),
+            Some(response) = received.recv() => {
+                let sent = matches!(response, Response::Sent(Ok(_)));
+                tui.handle_response(response);
+                if sent {
+                    fetch(&client, &mut tui, &responses);
+                }
+            }
+            event = events.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    match tui.handle_key(key) {
+                        Action::Nothing => {}
+                        Action::Send(request) => {
+                            let client = client.clone();
+                            let responses = responses.clone();
+                            tokio::spawn(async move {
+                                let result = client.send_message(&request).await;
+                                let _ = responses.send(Response::Sent(result));
+                            });
+                        }
+                        Action::Quit => return Ok(()),
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e),
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let client = match Client::new(&args.url, args.token) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, client).await;
+    ratatui::restore();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::style::Color;
+    use xivchat_client::models::ChatType;
+
+    use super::{new_request, ui::color};
+
+    #[test]
+    fn test_new_request() {
+        let request = new_request(&ChatType::TellOutgoing, "Alpha Beta@Phoenix hi there").unwrap();
+        assert_eq!(request.text, "hi there");
+        assert_eq!(request.target.unwrap().to_string(), "Alpha Beta@Phoenix");
+        assert!(new_request(&ChatType::TellOutgoing, "hi there").is_err());
+
+        let request = new_request(&ChatType::FreeCompany, "hi there").unwrap();
+        assert_eq!(request.text, "hi there");
+        assert!(request.target.is_none());
+    }
+
+    #[test]
+    fn test_color() {
+        assert_eq!(color(&ChatType::Yell), Color::Rgb(0xfc, 0xfc, 0x03));
+        assert_eq!(color(&ChatType::Say), Color::Reset);
+    }
+}
@@ -0,0 +1,113 @@
+use ratatui::{
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Wrap},
+    Frame,
+};
+use xivchat_client::models::{ChatMessage, ChatType, CROSS_WORLD_ICON};
+
+use super::Tui;
+
+const HELP: &str = "Tab: channel  ↑↓/PgUp/PgDn/End: scroll  Ctrl-R: reply to tell  Esc: quit";
+
+/// Terminal color for `ChatType::get_color`. The frontend's white is left to the terminal's
+/// default color, which also works on light themes.
+pub fn color(chat_type: &ChatType) -> Color {
+    let hex = chat_type.get_color();
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+    };
+    match (hex.len(), channel(1), channel(3), channel(5)) {
+        (7, Some(r), Some(g), Some(b)) => Color::Rgb(r, g, b),
+        _ => Color::Reset,
+    }
+}
+
+fn message_line(msg: &ChatMessage) -> Line<'_> {
+    let style = Style::new().fg(color(&msg.chat_type));
+    let time = msg
+        .parsed_timestamp()
+        .map(|ts| {
+            chrono::DateTime::<chrono::Local>::from(ts)
+                .format("%H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| "--:--".to_owned());
+
+    let mut spans = vec![Span::styled(format!("[{time}] "), Style::new().dark_gray())];
+    let label = msg.chat_type.to_string();
+    if !label.is_empty() {
+        spans.push(Span::styled(format!("[{label}] "), style));
+    }
+    if !msg.sender_name.is_empty() {
+        // the game font's cross world icon is not available in terminals
+        let sender = msg.sender_name.replace(CROSS_WORLD_ICON, "@");
+        spans.push(Span::styled(
+            format!("{sender}: "),
+            style.add_modifier(Modifier::BOLD),
+        ));
+    }
+    spans.push(Span::styled(msg.text.as_str(), style));
+    Line::from(spans)
+}
+
+fn draw_log(frame: &mut Frame, area: Rect, tui: &Tui) {
+    let mut block = Block::bordered().title(" XIVChat ");
+    if tui.scroll > 0 {
+        block = block.title_bottom(Line::from(format!(" {} newer ↓ ", tui.scroll)).right_aligned());
+    }
+    let inner = block.inner(area);
+
+    // every message takes at least one line
+    let end = tui.messages.len() - tui.scroll;
+    let start = end.saturating_sub(usize::from(inner.height));
+    let lines: Vec<Line> = tui.messages[start..end].iter().map(message_line).collect();
+    let log = Paragraph::new(lines).wrap(Wrap { trim: false });
+    let overflow = log
+        .line_count(inner.width)
+        .saturating_sub(usize::from(inner.height));
+    let log = log.scroll((u16::try_from(overflow).unwrap_or(u16::MAX), 0));
+
+    frame.render_widget(log.block(block), area);
+}
+
+fn draw_input(frame: &mut Frame, area: Rect, tui: &Tui) {
+    let chat_type = tui.chat_type();
+    let block = Block::bordered()
+        .title(format!(" {chat_type} "))
+        .border_style(Style::new().fg(color(chat_type)));
+    let inner = block.inner(area);
+
+    // keep the end of the input in view
+    let width = usize::from(inner.width.saturating_sub(1));
+    let chars = tui.input.chars().count();
+    let visible: String = tui
+        .input
+        .chars()
+        .skip(chars.saturating_sub(width))
+        .collect();
+    let cursor = u16::try_from(visible.chars().count()).unwrap_or(inner.width);
+
+    frame.render_widget(Paragraph::new(visible).block(block), area);
+    frame.set_cursor_position(Position::new(inner.x + cursor, inner.y));
+}
+
+pub fn draw(frame: &mut Frame, tui: &Tui) {
+    let [log, input, status] = Layout::vertical([
+        Constraint::Min(3),
+        Constraint::Length(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    draw_log(frame, log, tui);
+    draw_input(frame, input, tui);
+
+    let status_line = match &tui.error {
+        Some(e) => Line::from(e.to_string()).red(),
+        None => Line::from(HELP).dark_gray(),
+    };
+    frame.render_widget(status_line, status);
+}
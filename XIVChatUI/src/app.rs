@@ -1,49 +1,311 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    collections::BTreeMap,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
 
 const REFRESH_TIME_SEC: u64 = 3;
+const TOKEN_KEY: &str = "token";
+/// Number of failed connects after which the stream endpoint is considered unavailable.
+const MAX_STREAM_ATTEMPTS: u32 = 3;
+/// Seconds to wait for a sent message to show up in the chat before marking it as failed.
+const ECHO_TIMEOUT_SEC: u64 = 15;
 
-use models::{ChatMessage, NewMessageRequest};
+pub mod bridges;
+pub mod compose;
+pub mod export;
+pub mod history;
+pub mod notifications;
+pub mod outbox;
+pub mod payloads;
+pub mod search;
+mod storage;
+pub mod stream;
+pub mod tabs;
+#[cfg(test)]
+mod test_support;
+pub mod viewport;
+
+pub use xivchat_protocol as models;
+
+use bridges::{Bridge, BridgeSettings, BridgeUpdate, Source};
+use compose::{Drafts, HistorySearch, InputHistory, Recall, SplitSettings, SplitUpdate};
+use export::{ExportFormat, ExportSettings, ExportUpdate};
+use history::{History, HistorySettings, HistoryUpdate};
+use models::{
+    insert_messages, union_messages, ChatCommand, ChatMessage, ChatType, NewMessageRequest,
+    TellTarget, MAX_MESSAGE_BYTES,
+};
+use notifications::{NotificationSettings, NotificationUpdate};
+use outbox::{Outbox, OutboxAction, QueueState, QueuedMessage};
+use payloads::{shown_payloads, view_payloads};
 use requests::{get_messages, send_message};
-use wasm_bindgen::JsCast;
-use web_sys::{FormData, HtmlFormElement, HtmlInputElement};
-use yew::prelude::*;
+use search::{highlight, Matcher, SearchQuery, SearchUpdate};
+use stream::{MessageStream, StreamEvent};
+use tabs::{ChatTab, TabSettings, FILTER_CHANNELS};
+use viewport::Viewport;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    Element, FormData, HtmlElement, HtmlFormElement, HtmlInputElement, HtmlSelectElement,
+};
+use yew::{html::Scope, prelude::*};
+
+/// Values of the chatType select, as understood by `TryFrom<String> for ChatType`. Channels
+/// not marked as always available are only offered once there was traffic on them.
+const INPUT_CHANNELS: &[(&str, ChatType, bool)] = &[
+    ("Say", ChatType::Say, true),
+    ("Shout", ChatType::Shout, true),
+    ("Yell", ChatType::Yell, true),
+    ("Party", ChatType::Party, true),
+    ("Alliance", ChatType::Alliance, false),
+    ("FC", ChatType::FreeCompany, true),
+    ("Tell", ChatType::TellOutgoing, true),
+    ("PvP", ChatType::PvPTeam, false),
+    ("NN", ChatType::NoviceNetwork, false),
+    ("LS1", ChatType::LinkShell1, false),
+    ("LS2", ChatType::LinkShell2, false),
+    ("LS3", ChatType::LinkShell3, false),
+    ("LS4", ChatType::LinkShell4, false),
+    ("LS5", ChatType::LinkShell5, false),
+    ("LS6", ChatType::LinkShell6, false),
+    ("LS7", ChatType::LinkShell7, false),
+    ("LS8", ChatType::LinkShell8, false),
+    ("CWLS1", ChatType::CrossLinkShell1, false),
+    ("CWLS2", ChatType::CrossLinkShell2, false),
+    ("CWLS3", ChatType::CrossLinkShell3, false),
+    ("CWLS4", ChatType::CrossLinkShell4, false),
+    ("CWLS5", ChatType::CrossLinkShell5, false),
+    ("CWLS6", ChatType::CrossLinkShell6, false),
+    ("CWLS7", ChatType::CrossLinkShell7, false),
+    ("CWLS8", ChatType::CrossLinkShell8, false),
+];
 
 pub enum Msg {
     Nothing,
-    SetFetchState(Result<Vec<ChatMessage>, ErrorMessage>),
-    GetMessages,
-    SubmitMessage(Result<NewMessageRequest, String>),
+    SetFetchState(Source, Result<Vec<ChatMessage>, ErrorMessage>),
+    GetMessages(Source),
+    /// Fetches from every bridge.
+    Refresh,
+    /// Queues messages in order, parts of a split message are passed together.
+    SubmitMessage {
+        source: Source,
+        requests: Result<Vec<NewMessageRequest>, String>,
+    },
     ToggleRefresh,
+    ConnectStream(Source),
+    Stream(Source, StreamEvent),
+    SelectTab(usize),
+    AddTab,
+    RemoveTab(usize),
+    RenameTab(String),
+    ToggleTabChannel(ChatType),
+    ToggleTabEditor,
+    ToggleSearch,
+    Search(SearchUpdate),
+    JumpTo(String),
+    ToggleNotificationSettings,
+    Notifications(NotificationUpdate),
+    ToggleExport,
+    Export(ExportUpdate),
+    Download,
+    HistoryOpened(Result<(History, Vec<ChatMessage>), String>),
+    ToggleHistorySettings,
+    History(HistoryUpdate),
+    ClearHistory,
+    ToggleBridgeSettings,
+    Bridges(BridgeUpdate),
+    Scrolled {
+        offset: f64,
+        height: f64,
+    },
+    /// Renders again after rows were measured.
+    Layout,
+    ScrollToNewest,
+    Sent {
+        id: u64,
+        result: Result<(), ErrorMessage>,
+    },
+    FlushOutbox,
+    EchoTimeout(u64),
+    Outbox(OutboxAction),
 }
 
 pub enum RootMsg {
     SetChildCallback(Callback<Msg>),
+    SetChatType(String),
+    SetRecipient(String),
+    SetText(String),
+    /// Sends what was typed, or runs the chat command it starts with.
+    Submit,
+    /// Sends the parts of the previewed split message.
+    SendPreview,
+    CancelPreview,
+    Split(SplitUpdate),
+    /// Recalls the previously sent message, like Up in the game's chat input.
+    RecallOlder,
+    RecallNewer,
+    /// Starts searching the sent messages, or finds the next older match.
+    SearchOlder,
+    SearchQuery(String),
+    /// Puts the found message into the form.
+    AcceptSearch,
+    CancelSearch,
+    SetMessageInfo(MessageInfo),
+    /// The bridges messages can be sent through, with their names.
+    SetBridges(Vec<(Source, String)>),
+    /// Selects the bridge by its index in the list of `SetBridges`.
+    SetBridge(usize),
+    ReplyToLastTell,
+    /// Puts a queued message back into the input form.
+    EditMessage(NewMessageRequest, Source),
+    Unauthorized(ErrorMessage),
+    Login {
+        token: String,
+        remember: bool,
+    },
+    Logout,
 }
 
 pub struct State {
     pub refresh_enabled: bool,
 }
 
+#[derive(Default)]
+pub struct StreamState {
+    connection: Option<MessageStream>,
+    connected: bool,
+    /// Set once the stream was opened successfully, after which we never give up on it.
+    supported: bool,
+    attempts: u32,
+}
+
+/// Polling and streaming state of one bridge.
+pub struct Connection {
+    stream: StreamState,
+    /// Set when sending failed because the bridge was unreachable.
+    bridge_down: bool,
+    fetch_error: Option<ErrorMessage>,
+    fetch_queued: AtomicBool,
+}
+
+impl Connection {
+    fn new(bridge_down: bool) -> Self {
+        Connection {
+            stream: StreamState::default(),
+            bridge_down,
+            fetch_error: None,
+            // the first fetch is sent right away
+            fetch_queued: AtomicBool::new(true),
+        }
+    }
+
+    /// Whether the bridge rejected its token, it is not asked again until it gets a new one.
+    fn unauthorized(&self) -> bool {
+        self.fetch_error
+            .as_ref()
+            .is_some_and(|e| e.kind == ErrorKind::Unauthorized)
+    }
+}
+
 pub struct ChatBoxComponent {
+    parent: Scope<App>,
     state: State,
+    tabs: TabSettings,
+    editing_tab: bool,
+    search_open: bool,
+    search: SearchQuery,
+    /// Compiled `search`, `None` while the query is empty or invalid.
+    matcher: Option<Matcher>,
+    search_error: Option<String>,
+    /// Message that was jumped to from the search results.
+    jump_target: Option<String>,
+    scroll_to_jump_target: bool,
+    viewport: Viewport,
+    /// Keys of `rows()`, kept up to date by `rows_changed` so scrolling does not compute them.
+    row_keys: Vec<String>,
+    content_ref: NodeRef,
+    /// Messages that arrived while scrolled up.
+    unseen: usize,
+    notifications: NotificationSettings,
+    notification_settings_open: bool,
+    export_open: bool,
+    export: ExportSettings,
+    history_settings: HistorySettings,
+    history_settings_open: bool,
+    /// The opened message history, `None` while disabled or loading.
+    history: Option<History>,
+    /// Timestamp of the oldest message left after the retention limits removed older ones.
+    kept_since: Option<chrono::DateTime<chrono::Utc>>,
+    bridges: BridgeSettings,
+    bridge_settings_open: bool,
+    /// Why the last change of the bridge settings was refused.
+    bridge_error: Option<String>,
+    connections: BTreeMap<Source, Connection>,
+    outbox: Outbox,
     messages: Vec<ChatMessage>,
-    fetch_error: Option<ErrorMessage>,
-    fetch_queued: AtomicBool,
+    /// Why the last submitted message could not be queued.
+    input_error: Option<ErrorMessage>,
 }
 
 #[derive(Clone)]
 pub struct ErrorMessage {
+    kind: ErrorKind,
     description: String,
     details: Option<String>,
 }
 
-#[derive(Properties, PartialEq)]
-pub struct Props {
-    pub on_new_msg: Callback<Result<NewMessageRequest, String>>,
+#[derive(Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    /// The request could not be made or got no response.
+    Connection,
+    /// The bridge rejected the access token.
+    Unauthorized,
+    /// The bridge refused to accept a message.
+    Rejected,
+    /// Any other unsuccessful status code.
+    Http,
+    /// The response could not be read or parsed.
+    Parse,
+    /// Invalid user input, nothing was sent.
+    Input,
+}
+
+/// What the input form needs to know about the received messages.
+#[derive(Default, PartialEq)]
+pub struct MessageInfo {
+    last_tell: Option<TellTarget>,
+    /// The bridge the last tell was received through.
+    last_tell_bridge: Source,
+    seen_channels: Vec<ChatType>,
+}
+
+/// An over-long message split into parts, shown until sending them is confirmed.
+struct SplitPreview {
+    source: Source,
+    request: NewMessageRequest,
+    parts: Vec<NewMessageRequest>,
 }
 
 pub struct App {
     cb: Option<Callback<Msg>>,
+    chat_type: String,
+    recipient: String,
+    text: String,
+    drafts: Drafts,
+    history: InputHistory,
+    /// Channel and text of the form before recalling sent messages.
+    recall_draft: Option<(String, String)>,
+    history_search: Option<HistorySearch>,
+    /// Set when the search field appeared and needs the focus.
+    focus_search: bool,
+    split: SplitSettings,
+    preview: Option<SplitPreview>,
+    info: MessageInfo,
+    bridges: Vec<(Source, String)>,
+    /// The bridge messages are sent through.
+    bridge: Source,
+    /// Set while the login screen is shown, with the reason for it if any.
+    login: Option<Option<ErrorMessage>>,
 }
 
 impl Component for App {
@@ -52,64 +314,324 @@ impl Component for App {
     type Properties = ();
 
     fn create(_ctx: &Context<Self>) -> Self {
-        App { cb: None }
+        requests::set_token(storage::load(TOKEN_KEY));
+        let chat_type = INPUT_CHANNELS[0].0.to_owned();
+        let drafts = Drafts::load();
+        App {
+            cb: None,
+            recipient: String::new(),
+            text: drafts.get(&chat_type),
+            chat_type,
+            drafts,
+            history: InputHistory::load(),
+            recall_draft: None,
+            history_search: None,
+            focus_search: false,
+            split: SplitSettings::load(),
+            preview: None,
+            info: MessageInfo::default(),
+            bridges: Vec::new(),
+            bridge: None,
+            login: None,
+        }
     }
 
     fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             RootMsg::SetChildCallback(cb) => self.cb = Some(cb),
+            RootMsg::SetChatType(chat_type) => self.select_channel(chat_type),
+            RootMsg::SetRecipient(recipient) => self.recipient = recipient,
+            RootMsg::SetText(text) => {
+                self.drafts.set(&self.chat_type, &text);
+                self.text = text;
+                self.preview = None;
+                self.history.reset();
+            }
+            RootMsg::Submit => {
+                let (source, request) = match self.compose() {
+                    Ok(Some(composed)) => composed,
+                    // only switched the channel
+                    Ok(None) => return true,
+                    Err(e) => {
+                        self.submit(self.bridge.clone(), Err(e));
+                        return true;
+                    }
+                };
+                let requests = if request.game_bytes() <= MAX_MESSAGE_BYTES {
+                    self.remember(request.clone());
+                    Ok(vec![request])
+                } else if self.split.enabled {
+                    let parts = request.split(self.split.numbered);
+                    self.preview = Some(SplitPreview {
+                        source,
+                        request,
+                        parts,
+                    });
+                    return true;
+                } else {
+                    Err(format!(
+                        "The message is {} bytes long, the game only allows {MAX_MESSAGE_BYTES}",
+                        request.game_bytes()
+                    ))
+                };
+                self.submit(source, requests);
+            }
+            RootMsg::SendPreview => {
+                if let Some(preview) = self.preview.take() {
+                    self.remember(preview.request);
+                    self.submit(preview.source, Ok(preview.parts));
+                }
+            }
+            RootMsg::CancelPreview => self.preview = None,
+            RootMsg::Split(update) => {
+                self.split.apply(update);
+                self.preview = None;
+            }
+            RootMsg::RecallOlder => {
+                if !self.history.recalling() {
+                    self.recall_draft = Some((self.chat_type.clone(), self.text.clone()));
+                }
+                let Some(request) = self.history.older().cloned() else {
+                    return false;
+                };
+                self.recall(request);
+            }
+            RootMsg::RecallNewer => match self.history.newer() {
+                None => return false,
+                Some(Recall::Entry(request)) => {
+                    let request = request.clone();
+                    self.recall(request);
+                }
+                Some(Recall::Draft) => {
+                    if let Some((chat_type, text)) = self.recall_draft.take() {
+                        self.chat_type = chat_type;
+                        self.text = text;
+                    }
+                }
+            },
+            RootMsg::SearchOlder => {
+                self.focus_search = self.history_search.is_none();
+                let search = self
+                    .history_search
+                    .get_or_insert_with(HistorySearch::default);
+                search.found = self
+                    .history
+                    .search(&search.query, search.found)
+                    .or(search.found);
+            }
+            RootMsg::SearchQuery(query) => {
+                if let Some(search) = &mut self.history_search {
+                    search.found = self.history.search(&query, None);
+                    search.query = query;
+                }
+            }
+            RootMsg::AcceptSearch => {
+                let found = self
+                    .history_search
+                    .take()
+                    .and_then(|search| search.found)
+                    .and_then(|index| self.history.get(index).cloned());
+                if let Some(request) = found {
+                    self.history.reset();
+                    self.recall(request);
+                }
+                focus("text");
+            }
+            RootMsg::CancelSearch => {
+                self.history_search = None;
+                focus("text");
+            }
+            RootMsg::SetMessageInfo(info) => {
+                if self.info == info {
+                    return false;
+                }
+                self.info = info;
+            }
+            RootMsg::SetBridges(bridges) => {
+                if !bridges.iter().any(|(source, _)| *source == self.bridge) {
+                    self.bridge = None;
+                }
+                self.bridges = bridges;
+            }
+            RootMsg::SetBridge(index) => {
+                self.bridge = self
+                    .bridges
+                    .get(index)
+                    .and_then(|(source, _)| source.clone());
+            }
+            RootMsg::ReplyToLastTell => {
+                let Some(target) = &self.info.last_tell else {
+                    return false;
+                };
+                self.recipient = target.to_string();
+                if self.has_bridge(&self.info.last_tell_bridge) {
+                    self.bridge = self.info.last_tell_bridge.clone();
+                }
+                self.select_channel("Tell".to_owned());
+                focus("text");
+            }
+            RootMsg::EditMessage(msg, bridge) => {
+                if self.has_bridge(&bridge) {
+                    self.bridge = bridge;
+                }
+                self.recall(msg);
+                focus("text");
+            }
+            RootMsg::Unauthorized(e) => {
+                self.cb = None;
+                self.login = Some(Some(e));
+            }
+            RootMsg::Login { token, remember } => {
+                if remember {
+                    storage::save(TOKEN_KEY, &token);
+                } else {
+                    storage::remove(TOKEN_KEY);
+                }
+                requests::set_token(Some(token));
+                self.login = None;
+            }
+            RootMsg::Logout => {
+                storage::remove(TOKEN_KEY);
+                requests::set_token(None);
+                self.cb = None;
+                self.login = Some(None);
+            }
         }
         true
     }
 
-    fn view(&self, _ctx: &Context<Self>) -> Html {
-        let cb = self.cb.clone();
+    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+        if std::mem::take(&mut self.focus_search) {
+            focus("historySearch");
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let is_tell = self.chat_type == "Tell";
+        let recipient_error = (is_tell && !self.recipient.is_empty())
+            .then(|| self.recipient.parse::<TellTarget>().err())
+            .flatten();
+        let bytes = NewMessageRequest {
+            chat_type: ChatType::try_from(self.chat_type.clone()).unwrap_or(ChatType::Say),
+            text: self.text.clone(),
+            target: is_tell.then(|| self.recipient.parse().ok()).flatten(),
+        }
+        .game_bytes();
         html! {
             <>
             <div class="content">
                 <h1>{ "XIV Chat Bridge" }</h1>
+                if let Some(reason) = &self.login {
+                    { self.view_login(ctx, reason.as_ref()) }
+                } else {
                 <div class="chatBox">
                     <ChatBoxComponent />
                 </div>
                 <div class="chatInput">
-                    <form enctype={ "multipart/form-data" } onsubmit={move |e: SubmitEvent| {
+                    if let Some(preview) = &self.preview {
+                        { self.view_preview(ctx, &preview.parts) }
+                    }
+                    if let Some(search) = &self.history_search {
+                        { self.view_history_search(ctx, search) }
+                    }
+                    <form enctype={ "multipart/form-data" } onsubmit={ctx.link().callback(|e: SubmitEvent| {
                             e.prevent_default();
-                            if let Some(cb) = cb.as_ref() {
-                                let form = e
-                                    .target()
-                                    .and_then(|t| t.dyn_into::<HtmlFormElement>().ok());
-                                let res = if let Some(form) = form {
-                                    let data = FormData::new_with_form(&form)
-                                        .map_err(|e| e.as_string().unwrap_or_default())
-                                        .and_then(|form_data| form_data.try_into());
-                                    if let Some(input) = web_sys::window()
-                                        .and_then(|w| w.document())
-                                        .and_then(|doc| doc.get_element_by_id("text"))
-                                        .and_then(|elem| elem.dyn_into::<HtmlInputElement>().ok()) {
-                                            input.set_value("");
-                                        }
-                                    data
-                                } else {
-                                    Err("unable to get form reference".to_owned())
-                                };
-                                cb.emit(Msg::SubmitMessage(res));
-                            }
-                        }}>
+                            RootMsg::Submit
+                        })}>
+                        if self.bridges.len() > 1 {
+                            <label for="bridge">{ "Bridge:" }</label>
+                            <select name="bridge" id="bridge" onchange={ctx.link().batch_callback(|e: Event| {
+                                    e.target_dyn_into::<HtmlSelectElement>()
+                                        .and_then(|select| select.value().parse::<usize>().ok())
+                                        .map(RootMsg::SetBridge)
+                                })}>
+                                {
+                                    self.bridges.iter().enumerate().map(|(index, (source, name))| html! {
+                                        <option value={index.to_string()} selected={*source == self.bridge}>{ name.clone() }</option>
+                                    }).collect::<Html>()
+                                }
+                            </select>
+                        }
                         <label for="chatType">{ "ChatType:" }</label>
-                        <select name="chatType" id="chatType">
-                            <option value="Say" selected=true >{ "Say" }</option>
-                            <option value="Shout">{ "Shout" }</option>
-                            <option value="Yell">{ "Yell" }</option>
-                            <option value="Party">{ "Party" }</option>
-                            <option value="FC">{ "FC" }</option>
+                        <select name="chatType" id="chatType" onchange={ctx.link().callback(|e: Event| {
+                                let value = e
+                                    .target_dyn_into::<HtmlSelectElement>()
+                                    .map(|select| select.value())
+                                    .unwrap_or_default();
+                                RootMsg::SetChatType(value)
+                            })}>
+                            {
+                                INPUT_CHANNELS.iter()
+                                    .filter(|(value, chat_type, always)| {
+                                        *always || self.chat_type == *value || self.info.seen_channels.contains(chat_type)
+                                    })
+                                    .map(|(value, _, _)| html! {
+                                        <option value={*value} selected={self.chat_type == *value}>{ *value }</option>
+                                    }).collect::<Html>()
+                            }
                         </select>
+                        if is_tell {
+                            <label for="recipient">{ "Recipient:" }</label>
+                            <input type="text" id="recipient" name="recipient" class={classes!("recipient", recipient_error.is_some().then_some("invalid"))}
+                                placeholder="First Last@World" title={recipient_error.clone().unwrap_or_default()}
+                                value={self.recipient.clone()} oninput={ctx.link().callback(|e: InputEvent| {
+                                    let value = e
+                                        .target_dyn_into::<HtmlInputElement>()
+                                        .map(|input| input.value())
+                                        .unwrap_or_default();
+                                    RootMsg::SetRecipient(value)
+                                })} />
+                        }
+                        if let Some(target) = &self.info.last_tell {
+                            <button type="button" class="reply" title={format!("Reply to {target}")}
+                                onclick={ctx.link().callback(|_| RootMsg::ReplyToLastTell)}>{ "↩" }</button>
+                        }
                         <label for="text">{ "Message:" }</label>
-                        <input type="text" id="text" name="text" />
+                        <input type="text" id="text" name="text" value={self.text.clone()}
+                            title="Chat commands like /p, /fc, /ls1, /cwl1, /t First Last@World or /r pick the channel" oninput={ctx.link().callback(|e: InputEvent| {
+                                let value = e
+                                    .target_dyn_into::<HtmlInputElement>()
+                                    .map(|input| input.value())
+                                    .unwrap_or_default();
+                                RootMsg::SetText(value)
+                            })}
+                            onkeydown={ctx.link().batch_callback(|e: KeyboardEvent| {
+                                let msg = match e.key().as_str() {
+                                    "ArrowUp" => RootMsg::RecallOlder,
+                                    "ArrowDown" => RootMsg::RecallNewer,
+                                    "r" | "R" if e.ctrl_key() => RootMsg::SearchOlder,
+                                    _ => return None,
+                                };
+                                e.prevent_default();
+                                Some(msg)
+                            })} />
+                        <span class={classes!("byteCounter", (bytes > MAX_MESSAGE_BYTES).then_some("over"))}
+                            title="Bytes of the message, the game's limit">{ format!("{bytes}/{MAX_MESSAGE_BYTES}") }</span>
                         <button type="submit">{ "Send" }</button>
+                        <label class="splitOption" title="Send messages over the limit as several, after a preview">
+                            <input type="checkbox" checked={self.split.enabled} onclick={ctx.link().callback({
+                                let enabled = self.split.enabled;
+                                move |_| RootMsg::Split(SplitUpdate::Enabled(!enabled))
+                            })} />
+                            { "Split" }
+                        </label>
+                        if self.split.enabled {
+                            <label class="splitOption">
+                                <input type="checkbox" checked={self.split.numbered} onclick={ctx.link().callback({
+                                    let numbered = self.split.numbered;
+                                    move |_| RootMsg::Split(SplitUpdate::Numbered(!numbered))
+                                })} />
+                                { "(1/n)" }
+                            </label>
+                        }
                     </form>
                 </div>
+                }
             </div>
             <footer>
+                if self.login.is_none() && requests::token(None).is_some() {
+                    <button type="button" class="logout" onclick={ctx.link().callback(|_| RootMsg::Logout)}>{ "Log out" }</button>
+                }
                 { "Made by Elena" }
             </footer>
             </>
@@ -117,6 +639,193 @@ impl Component for App {
     }
 }
 
+impl App {
+    /// The typed message, with the chat command it starts with applied. `None` if the command
+    /// only switched the channel.
+    fn compose(&mut self) -> Result<Option<(Source, NewMessageRequest)>, String> {
+        let (source, chat_type, target, text) = match ChatCommand::parse(&self.text)? {
+            None => {
+                let chat_type = ChatType::try_from(self.chat_type.clone())?;
+                let target = if chat_type == ChatType::TellOutgoing {
+                    Some(self.recipient.parse::<TellTarget>()?)
+                } else {
+                    None
+                };
+                let request = NewMessageRequest {
+                    chat_type,
+                    text: self.text.clone(),
+                    target,
+                };
+                return Ok(Some((self.bridge.clone(), request)));
+            }
+            Some(ChatCommand::Channel {
+                chat_type,
+                target,
+                text,
+            }) => (self.bridge.clone(), chat_type, target, text),
+            Some(ChatCommand::Reply(text)) => {
+                let target = self
+                    .info
+                    .last_tell
+                    .clone()
+                    .ok_or_else(|| "There is no tell to reply to".to_owned())?;
+                // answered by the character that got the tell
+                let source = if self.has_bridge(&self.info.last_tell_bridge) {
+                    self.info.last_tell_bridge.clone()
+                } else {
+                    self.bridge.clone()
+                };
+                (source, ChatType::TellOutgoing, Some(target), text)
+            }
+        };
+
+        if text.is_empty() {
+            // like the game, a command without text switches the channel
+            self.text.clear();
+            self.drafts.set(&self.chat_type, "");
+            if let Some((value, _, _)) = INPUT_CHANNELS.iter().find(|(_, c, _)| *c == chat_type) {
+                self.select_channel((*value).to_owned());
+            }
+            if let Some(target) = target {
+                self.recipient = target.to_string();
+            }
+            self.bridge = source;
+            return Ok(None);
+        }
+        Ok(Some((
+            source,
+            NewMessageRequest {
+                chat_type,
+                text,
+                target,
+            },
+        )))
+    }
+
+    fn has_bridge(&self, source: &Source) -> bool {
+        self.bridges.iter().any(|(bridge, _)| bridge == source)
+    }
+
+    /// Hands messages to the chat box to send through `source`.
+    fn submit(&mut self, source: Source, requests: Result<Vec<NewMessageRequest>, String>) {
+        let Some(cb) = &self.cb else {
+            return;
+        };
+        if requests.is_ok() {
+            self.text.clear();
+            self.drafts.set(&self.chat_type, "");
+        }
+        cb.emit(Msg::SubmitMessage { source, requests });
+    }
+
+    /// Switches the form to another channel, swapping in its draft.
+    fn select_channel(&mut self, chat_type: String) {
+        if chat_type == self.chat_type {
+            return;
+        }
+        self.chat_type = chat_type;
+        self.text = self.drafts.get(&self.chat_type);
+        self.preview = None;
+        self.history.reset();
+    }
+
+    /// Adds a sent message to the input history.
+    fn remember(&mut self, request: NewMessageRequest) {
+        self.history.push(request);
+        self.history.save();
+    }
+
+    /// Puts a message back into the form, with its channel.
+    fn recall(&mut self, request: NewMessageRequest) {
+        if let Some((value, _, _)) = INPUT_CHANNELS
+            .iter()
+            .find(|(_, chat_type, _)| *chat_type == request.chat_type)
+        {
+            self.chat_type = (*value).to_owned();
+        }
+        if let Some(target) = &request.target {
+            self.recipient = target.to_string();
+        }
+        self.text = request.text;
+        self.preview = None;
+    }
+
+    fn view_history_search(&self, ctx: &Context<Self>, search: &HistorySearch) -> Html {
+        let found = search.found.and_then(|index| self.history.get(index));
+        html! {
+            <div class="historySearch">
+                <input type="text" id="historySearch" placeholder="Search sent messages" value={search.query.clone()}
+                    title="Enter to use the match, Ctrl+R for an older one, Escape to cancel"
+                    oninput={ctx.link().callback(|e: InputEvent| {
+                        let value = e
+                            .target_dyn_into::<HtmlInputElement>()
+                            .map(|input| input.value())
+                            .unwrap_or_default();
+                        RootMsg::SearchQuery(value)
+                    })}
+                    onkeydown={ctx.link().batch_callback(|e: KeyboardEvent| {
+                        let msg = match e.key().as_str() {
+                            "Enter" => RootMsg::AcceptSearch,
+                            "Escape" => RootMsg::CancelSearch,
+                            "r" | "R" if e.ctrl_key() => RootMsg::SearchOlder,
+                            _ => return None,
+                        };
+                        e.prevent_default();
+                        Some(msg)
+                    })} />
+                <span class="historyMatch">
+                    {
+                        found.map_or_else(
+                            || "No match".to_owned(),
+                            |request| format!("[{}] {}", request.chat_type, request.text),
+                        )
+                    }
+                </span>
+            </div>
+        }
+    }
+
+    fn view_preview(&self, ctx: &Context<Self>, parts: &[NewMessageRequest]) -> Html {
+        html! {
+            <div class="splitPreview">
+                <span>{ format!("The message is too long for the game and will be sent as {} messages:", parts.len()) }</span>
+                <ol>
+                    { parts.iter().map(|part| html! { <li>{ part.text.clone() }</li> }).collect::<Html>() }
+                </ol>
+                <div class="splitActions">
+                    <button type="button" onclick={ctx.link().callback(|_| RootMsg::SendPreview)}>{ format!("Send {} messages", parts.len()) }</button>
+                    <button type="button" onclick={ctx.link().callback(|_| RootMsg::CancelPreview)}>{ "Cancel" }</button>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_login(&self, ctx: &Context<Self>, reason: Option<&ErrorMessage>) -> Html {
+        html! {
+            <form class="login" onsubmit={ctx.link().batch_callback(|e: SubmitEvent| {
+                e.prevent_default();
+                let data = e
+                    .target_dyn_into::<HtmlFormElement>()
+                    .and_then(|form| FormData::new_with_form(&form).ok())?;
+                let token = data.get("token").as_string().filter(|token| !token.is_empty())?;
+                let remember = data.get("remember").as_string().is_some();
+                Some(RootMsg::Login { token, remember })
+            })}>
+                if let Some(reason) = reason {
+                    <span class="errorMessage">{ reason.description.clone() }</span>
+                }
+                <label for="token">{ "Access token or password" }</label>
+                <input type="password" id="token" name="token" autocomplete="current-password" />
+                <div class="remember">
+                    <input type="checkbox" id="remember" name="remember" />
+                    <label for="remember">{ "Remember me" }</label>
+                </div>
+                <button type="submit">{ "Log in" }</button>
+            </form>
+        }
+    }
+}
+
 impl Component for ChatBoxComponent {
     type Message = Msg;
     type Properties = ();
@@ -130,15 +839,73 @@ impl Component for ChatBoxComponent {
             .expect("this needs to not be orphaned..");
         let parent = parent_link.downcast::<App>();
         parent.send_message(RootMsg::SetChildCallback(cb));
-        ctx.link().send_message(Msg::GetMessages);
-        ChatBoxComponent {
+        let history_settings = HistorySettings::load();
+        if history_settings.enabled {
+            // the first fetch only needs what is newer than the history
+            open_history(ctx);
+        } else {
+            ctx.link().send_message(Msg::Refresh);
+        }
+        // tokens of other bridges are kept like the one of the login, if there is one
+        let remember_tokens =
+            requests::token(None).is_none() || storage::load::<String>(TOKEN_KEY).is_some();
+        let bridges = BridgeSettings::load(remember_tokens);
+        requests::set_base_url(bridges.primary_url.clone());
+        for source in bridges.sources() {
+            ctx.link().send_message(Msg::ConnectStream(source));
+        }
+        let tabs = TabSettings::load();
+        let outbox = Outbox::load();
+        for msg in &outbox.messages {
+            if msg.state == QueueState::Sent {
+                wait_for_echo(ctx, msg.id);
+            }
+        }
+        // queued messages are sent once the first fetch reaches their bridge
+        let connections = bridges
+            .sources()
+            .map(|source| {
+                let connection = Connection::new(outbox.has_pending(&source));
+                (source, connection)
+            })
+            .collect();
+        select_primary_channel(&parent, tabs.active_tab());
+        let mut component = ChatBoxComponent {
+            parent,
             state: State {
                 refresh_enabled: true,
             },
+            tabs,
+            editing_tab: false,
+            search_open: false,
+            search: SearchQuery::default(),
+            matcher: None,
+            search_error: None,
+            jump_target: None,
+            scroll_to_jump_target: false,
+            viewport: Viewport::default(),
+            row_keys: Vec::new(),
+            content_ref: NodeRef::default(),
+            unseen: 0,
+            notifications: NotificationSettings::load(),
+            notification_settings_open: false,
+            export_open: false,
+            export: ExportSettings::default(),
+            history_settings,
+            history_settings_open: false,
+            history: None,
+            kept_since: None,
+            bridges,
+            bridge_settings_open: false,
+            bridge_error: None,
+            connections,
+            outbox,
             messages: Vec::new(),
-            fetch_error: None,
-            fetch_queued: AtomicBool::new(true),
-        }
+            input_error: None,
+        };
+        component.rows_changed();
+        component.bridges_changed();
+        component
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
@@ -146,324 +913,1254 @@ impl Component for ChatBoxComponent {
             Msg::Nothing => false,
             Msg::ToggleRefresh => {
                 self.state.refresh_enabled = !self.state.refresh_enabled;
-                if self.state.refresh_enabled && !self.fetch_queued.load(Ordering::Relaxed) {
-                    self.fetch_queued.store(true, Ordering::Relaxed);
-                    ctx.link().send_future(async move {
-                        wasmtimer::tokio::sleep(std::time::Duration::from_secs(REFRESH_TIME_SEC))
-                            .await;
-                        Msg::GetMessages
-                    });
+                for (source, connection) in &mut self.connections {
+                    if self.state.refresh_enabled {
+                        ctx.link().send_message(Msg::ConnectStream(source.clone()));
+                    } else {
+                        connection.stream.connection = None;
+                        connection.stream.connected = false;
+                    }
+                }
+                for source in self.connections.keys() {
+                    self.queue_fetch(ctx, source, Duration::from_secs(REFRESH_TIME_SEC));
+                }
+                false
+            }
+            Msg::ConnectStream(source) => {
+                let Some(connection) = self.connections.get_mut(&source) else {
+                    return false;
+                };
+                let unauthorized = connection.unauthorized();
+                let stream = &mut connection.stream;
+                let gave_up = !stream.supported && stream.attempts >= MAX_STREAM_ATTEMPTS;
+                // a token in the stream URL would end up in logs and the browser history, bridges
+                // that need one are polled with the header instead
+                let needs_token = requests::token(self.bridges.get(&source)).is_some();
+                if !self.state.refresh_enabled
+                    || stream.connection.is_some()
+                    || gave_up
+                    || needs_token
+                    || unauthorized
+                {
+                    return false;
+                }
+                let on_event = {
+                    let source = source.clone();
+                    ctx.link()
+                        .callback(move |event| Msg::Stream(source.clone(), event))
+                };
+                match MessageStream::connect(self.bridges.get(&source), on_event) {
+                    Ok(connection) => stream.connection = Some(connection),
+                    Err(e) => {
+                        log::error!("{}", e.description);
+                        ctx.link()
+                            .send_message(Msg::Stream(source, StreamEvent::Closed));
+                    }
                 }
                 false
             }
-            Msg::SetFetchState(state) => {
+            Msg::Stream(source, event) => {
+                let Some(connection) = self.connections.get_mut(&source) else {
+                    return false;
+                };
+                let stream = &mut connection.stream;
+                match event {
+                    StreamEvent::Opened => {
+                        stream.connected = true;
+                        stream.supported = true;
+                        stream.attempts = 0;
+                        // catch up on anything missed while the stream was down
+                        ctx.link().send_message(Msg::GetMessages(source.clone()));
+                        self.bridge_up(ctx, &source);
+                        false
+                    }
+                    StreamEvent::Message(msg) => {
+                        let (added, new) = self.receive(&source, vec![msg]);
+                        if !added.is_empty() {
+                            self.messages_changed(ctx, &new);
+                            self.keep_history(&added);
+                        }
+                        !added.is_empty()
+                    }
+                    StreamEvent::Closed => {
+                        stream.connection = None;
+                        stream.connected = false;
+                        stream.attempts += 1;
+                        if !stream.supported && stream.attempts >= MAX_STREAM_ATTEMPTS {
+                            log::info!(
+                                "Message stream of {} unavailable, using polling only",
+                                self.bridges.name(&source)
+                            );
+                        } else {
+                            let delay = stream::backoff(stream.attempts);
+                            let source = source.clone();
+                            ctx.link().send_future(async move {
+                                wasmtimer::tokio::sleep(delay).await;
+                                Msg::ConnectStream(source)
+                            });
+                        }
+                        self.queue_fetch(ctx, &source, Duration::ZERO);
+                        false
+                    }
+                }
+            }
+            Msg::SetFetchState(source, state) => {
+                let Some(connection) = self.connections.get_mut(&source) else {
+                    return false;
+                };
                 match state {
                     Ok(messages) => {
-                        self.fetch_error = None;
-                        self.messages = messages
+                        connection.fetch_error = None;
+                        let initial = !self.messages.iter().any(|msg| msg.source == source);
+                        let (added, new) = self.receive(&source, messages);
+                        if initial {
+                            // sent before the page was reloaded
+                            self.confirm_sent(&added);
+                        }
+                        self.messages_changed(ctx, if initial { &[] } else { &new });
+                        self.keep_history(&added);
+                        self.bridge_up(ctx, &source);
                     }
-                    Err(e) => self.fetch_error = Some(e),
+                    // the login screen is only about the bridge serving the page
+                    Err(e) if e.kind == ErrorKind::Unauthorized && source.is_none() => {
+                        self.parent.send_message(RootMsg::Unauthorized(e));
+                        return false;
+                    }
+                    Err(e) if e.kind == ErrorKind::Unauthorized => {
+                        self.bridge_unauthorized(&source, e);
+                        return true;
+                    }
+                    Err(e) => connection.fetch_error = Some(e),
                 }
 
-                if self.state.refresh_enabled && !self.fetch_queued.load(Ordering::Relaxed) {
-                    self.fetch_queued.store(true, Ordering::Relaxed);
-                    ctx.link().send_future(async move {
-                        wasmtimer::tokio::sleep(std::time::Duration::from_secs(REFRESH_TIME_SEC))
-                            .await;
-                        Msg::GetMessages
-                    });
+                if !self
+                    .connections
+                    .get(&source)
+                    .is_some_and(|connection| connection.stream.connected)
+                {
+                    self.queue_fetch(ctx, &source, Duration::from_secs(REFRESH_TIME_SEC));
                 }
                 true
             }
-            Msg::GetMessages => {
-                self.fetch_queued.store(false, Ordering::Relaxed);
-                ctx.link()
-                    .send_future(async { Msg::SetFetchState(get_messages().await) });
+            Msg::GetMessages(source) => {
+                let Some(connection) = self.connections.get(&source) else {
+                    return false;
+                };
+                connection.fetch_queued.store(false, Ordering::Relaxed);
+                // every bridge gets its own cursor, their clocks need not agree
+                let since = self
+                    .messages
+                    .iter()
+                    .rev()
+                    .find(|msg| msg.source == source)
+                    .map(|msg| msg.timestamp.clone());
+                let bridge = self.bridges.get(&source).cloned();
+                ctx.link().send_future(async move {
+                    let result = get_messages(bridge.as_ref(), since.as_deref()).await;
+                    Msg::SetFetchState(source, result)
+                });
+                false
+            }
+            Msg::Refresh => {
+                for source in self.connections.keys() {
+                    ctx.link().send_message(Msg::GetMessages(source.clone()));
+                }
                 false
             }
-            Msg::SubmitMessage(msg) => {
-                match msg {
-                    Ok(msg) => {
-                        let msg = msg.clone();
-                        ctx.link().send_future(async move {
-                            match send_message(&msg).await {
-                                Ok(_) => {
-                                    wasmtimer::tokio::sleep(std::time::Duration::from_secs(1))
-                                        .await;
-                                    Msg::GetMessages
+            Msg::SubmitMessage { source, requests } => {
+                match requests {
+                    Ok(requests) => {
+                        self.input_error = None;
+                        for request in requests {
+                            self.outbox.push(request, source.clone());
+                        }
+                        self.outbox.save();
+                        self.rows_changed();
+                        self.flush_outbox(ctx);
+                    }
+                    Err(e) => {
+                        log::error!("{e}");
+                        self.input_error = Some(ErrorMessage {
+                            kind: ErrorKind::Input,
+                            description: e,
+                            details: None,
+                        });
+                    }
+                }
+                true
+            }
+            Msg::Sent { id, result } => {
+                let bridge = self.outbox.get_mut(id).and_then(|msg| msg.bridge.clone());
+                match result {
+                    Ok(()) => {
+                        self.outbox.sent(id);
+                        wait_for_echo(ctx, id);
+                        if !self
+                            .connections
+                            .get(&bridge)
+                            .is_some_and(|connection| connection.stream.connected)
+                        {
+                            ctx.link().send_future(async {
+                                wasmtimer::tokio::sleep(Duration::from_secs(1)).await;
+                                Msg::GetMessages(bridge)
+                            });
+                        }
+                        self.flush_outbox(ctx);
+                    }
+                    Err(e) => {
+                        let retry = self.outbox.failed(id, &e);
+                        match e.kind {
+                            ErrorKind::Unauthorized if bridge.is_none() => {
+                                self.outbox.save();
+                                self.parent.send_message(RootMsg::Unauthorized(e));
+                                return false;
+                            }
+                            // sent once the bridge was added again with a valid token
+                            ErrorKind::Unauthorized => self.bridge_unauthorized(&bridge, e),
+                            // sent again as soon as the bridge answers
+                            ErrorKind::Connection => {
+                                if let Some(connection) = self.connections.get_mut(&bridge) {
+                                    connection.bridge_down = true;
                                 }
-                                Err(e) => Msg::SetFetchState(Err(e)),
                             }
-                        });
+                            _ if retry => {
+                                let attempts =
+                                    self.outbox.get_mut(id).map_or(1, |msg| msg.attempts);
+                                let delay = stream::backoff(attempts);
+                                ctx.link().send_future(async move {
+                                    wasmtimer::tokio::sleep(delay).await;
+                                    Msg::FlushOutbox
+                                });
+                            }
+                            _ => self.flush_outbox(ctx),
+                        }
                     }
-                    Err(e) => log::error!("{e}"),
                 }
+                self.outbox.save();
+                true
+            }
+            Msg::FlushOutbox => {
+                self.flush_outbox(ctx);
+                true
+            }
+            Msg::EchoTimeout(id) => {
+                let changed = self.outbox.unconfirmed(id);
+                if changed {
+                    self.outbox.save();
+                }
+                changed
+            }
+            Msg::Outbox(action) => {
+                match action {
+                    OutboxAction::Resend(id) => {
+                        if let Some(msg) = self.outbox.get_mut(id) {
+                            msg.state = QueueState::Pending;
+                            msg.error = None;
+                        }
+                        self.flush_outbox(ctx);
+                    }
+                    OutboxAction::Edit(id) => {
+                        if let Some(msg) = self.outbox.remove(id) {
+                            self.parent
+                                .send_message(RootMsg::EditMessage(msg.request, msg.bridge));
+                        }
+                    }
+                    OutboxAction::Discard(id) => {
+                        self.outbox.remove(id);
+                    }
+                }
+                self.outbox.save();
+                self.rows_changed();
+                true
+            }
+            Msg::SelectTab(index) => {
+                self.tabs.select(index);
+                self.rows_changed();
+                self.viewport.scroll_to_bottom();
+                self.unseen = 0;
+                select_primary_channel(&self.parent, self.tabs.active_tab());
+                true
+            }
+            Msg::AddTab => {
+                self.tabs.tabs.push(ChatTab::new("New Tab", Vec::new()));
+                self.tabs.select(self.tabs.tabs.len() - 1);
+                self.rows_changed();
+                self.editing_tab = true;
+                true
+            }
+            Msg::RemoveTab(index) => {
+                self.tabs.remove(index);
+                self.rows_changed();
+                select_primary_channel(&self.parent, self.tabs.active_tab());
+                true
+            }
+            Msg::RenameTab(name) => {
+                self.tabs.active_tab_mut().name = name;
+                self.tabs.save();
+                true
+            }
+            Msg::ToggleTabChannel(chat_type) => {
+                let channels = &mut self.tabs.active_tab_mut().channels;
+                if let Some(index) = channels.iter().position(|c| *c == chat_type) {
+                    channels.remove(index);
+                } else {
+                    channels.push(chat_type);
+                }
+                self.tabs.save();
+                self.rows_changed();
+                select_primary_channel(&self.parent, self.tabs.active_tab());
+                true
+            }
+            Msg::ToggleTabEditor => {
+                self.editing_tab = !self.editing_tab;
+                true
+            }
+            Msg::ToggleSearch => {
+                self.search_open = !self.search_open;
+                self.rows_changed();
+                true
+            }
+            Msg::Search(update) => {
+                self.search.apply(update);
+                (self.matcher, self.search_error) = if self.search.is_empty() {
+                    (None, None)
+                } else {
+                    match self.search.compile() {
+                        Ok(matcher) => (Some(matcher), None),
+                        Err(e) => (None, Some(e)),
+                    }
+                };
+                self.rows_changed();
+                true
+            }
+            Msg::JumpTo(key) => {
+                let Some(msg) = self.messages.iter().find(|msg| message_key(msg) == key) else {
+                    return false;
+                };
+                if !self.tabs.active_tab().shows(&msg.chat_type) {
+                    if let Some(index) = self
+                        .tabs
+                        .tabs
+                        .iter()
+                        .position(|tab| tab.shows(&msg.chat_type))
+                    {
+                        self.tabs.select(index);
+                    }
+                }
+                self.search_open = false;
+                self.rows_changed();
+                self.jump_target = Some(key);
+                self.scroll_to_jump_target = true;
+                true
+            }
+            Msg::ToggleNotificationSettings => {
+                self.notification_settings_open = !self.notification_settings_open;
+                true
+            }
+            Msg::Notifications(NotificationUpdate::Enabled(true))
+                if notifications::permission()
+                    != Some(web_sys::NotificationPermission::Granted) =>
+            {
+                ctx.link().send_future(async {
+                    let granted = notifications::request_permission().await;
+                    Msg::Notifications(NotificationUpdate::Enabled(granted))
+                });
                 false
             }
+            Msg::Notifications(update) => {
+                self.notifications.apply(update);
+                true
+            }
+            Msg::ToggleExport => {
+                self.export_open = !self.export_open;
+                true
+            }
+            Msg::Export(update) => {
+                self.export.apply(update);
+                true
+            }
+            Msg::Download => {
+                let filtered = self.export.filtered;
+                let messages = self
+                    .messages
+                    .iter()
+                    .filter(|msg| !filtered || self.shows(msg));
+                if let Err(e) = self.export.format.download(messages) {
+                    log::error!("Unable to export messages: {e:?}");
+                }
+                false
+            }
+            Msg::HistoryOpened(result) => {
+                ctx.link().send_message(Msg::Refresh);
+                match result {
+                    // disabled while it was loading
+                    Ok(_) if !self.history_settings.enabled => false,
+                    Ok((history, stored)) => {
+                        // received while the history was loading
+                        let received = self.messages.clone();
+                        let saved = history.clone();
+                        update_history(async move { saved.save(&received).await });
+                        union_messages(&mut self.messages, stored);
+                        self.history = Some(history);
+                        self.keep_history(&[]);
+                        self.messages_changed(ctx, &[]);
+                        true
+                    }
+                    Err(e) => {
+                        log::error!("Unable to open message history: {e}");
+                        false
+                    }
+                }
+            }
+            Msg::ToggleHistorySettings => {
+                self.history_settings_open = !self.history_settings_open;
+                true
+            }
+            Msg::History(update) => {
+                self.history_settings.apply(update);
+                if !self.history_settings.enabled {
+                    // nothing is kept on the device once disabled
+                    if let Some(history) = self.history.take() {
+                        update_history(async move { history.clear().await });
+                    }
+                } else if self.history.is_none() {
+                    open_history(ctx);
+                } else {
+                    self.keep_history(&[]);
+                }
+                true
+            }
+            Msg::ClearHistory => {
+                if let Some(history) = self.history.clone() {
+                    update_history(async move { history.clear().await });
+                }
+                // start over with what the bridge still has
+                let cleared = self.messages.drain(..).map(|msg| message_key(&msg));
+                self.viewport.forget(cleared);
+                self.rows_changed();
+                self.jump_target = None;
+                ctx.link().send_message(Msg::Refresh);
+                true
+            }
+            Msg::ToggleBridgeSettings => {
+                self.bridge_settings_open = !self.bridge_settings_open;
+                true
+            }
+            Msg::Bridges(update) => {
+                let primary_url = self.bridges.primary_url.clone();
+                if let Err(e) = self.bridges.apply(update) {
+                    self.bridge_error = Some(e);
+                    return true;
+                }
+                self.bridge_error = None;
+                if self.bridges.primary_url != primary_url {
+                    requests::set_base_url(self.bridges.primary_url.clone());
+                    // connects again below
+                    self.connections.remove(&None);
+                }
+                // drops the streams of removed bridges
+                self.connections
+                    .retain(|source, _| source.is_none() || self.bridges.get(source).is_some());
+                for source in self.bridges.sources() {
+                    if !self.connections.contains_key(&source) {
+                        let connection = Connection::new(self.outbox.has_pending(&source));
+                        self.connections.insert(source.clone(), connection);
+                        ctx.link().send_message(Msg::ConnectStream(source.clone()));
+                        ctx.link().send_message(Msg::GetMessages(source));
+                    }
+                }
+                self.bridges_changed();
+                true
+            }
+            Msg::Scrolled { offset, height } => {
+                self.viewport.scrolled(&self.row_keys, offset, height);
+                let seen = self.unseen > 0 && self.viewport.at_bottom();
+                if seen {
+                    self.unseen = 0;
+                }
+                seen || self.viewport.needs_render(&self.row_keys)
+            }
+            Msg::Layout => true,
+            Msg::ScrollToNewest => {
+                self.viewport.scroll_to_bottom();
+                self.unseen = 0;
+                true
+            }
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, _first_render: bool) {
+        let Some(content) = self.content_ref.cast::<HtmlElement>() else {
+            return;
+        };
+        let keys = &self.row_keys;
+        let rows = content.children();
+        let mut changed = self
+            .viewport
+            .measure((0..rows.length()).filter_map(|index| {
+                let row = rows.item(index)?.dyn_into::<HtmlElement>().ok()?;
+                (!row.id().is_empty()).then(|| (row.id(), f64::from(row.offset_height())))
+            }));
+        let height = f64::from(content.client_height());
+        changed |= (height - self.viewport.height).abs() >= 1.0;
+        self.viewport.height = height;
+
+        let jumped = std::mem::take(&mut self.scroll_to_jump_target)
+            && self
+                .jump_target
+                .as_ref()
+                .is_some_and(|key| self.viewport.scroll_to(keys, key));
+        let offset = self.viewport.anchored_offset(keys);
+        self.viewport.offset = offset;
+        // column-reverse scrolls towards negative values
+        if (offset + f64::from(content.scroll_top())).abs() >= 1.0 {
+            content.set_scroll_top(-(offset.round() as i32));
+        }
+        if changed || jumped {
+            ctx.link().send_message(Msg::Layout);
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
+        let matcher = self.matcher.as_ref().filter(|_| self.search_open);
+        let rows = self.rows();
+        let window = self.viewport.render(&self.row_keys);
         html! {
         <>
-        if let Some(err) = self.fetch_error.clone() {
-            <div class="errorReport">
-                <span class="errorMessage">{ err.description.clone() }</span>
-                <button type="button" title="Copy details" onclick={ctx.link().callback(move |_| {
-                    if let Some(details) = &err.details {
-                        if let Some(clipboard) = web_sys::window()
-                            .and_then(|w| w.navigator().clipboard()) {
-                                let _ = clipboard.write_text(details);
-                            }
-                        }
-                        Msg::Nothing
-                    }
-                )
-
-                }>{ "📄" }</button>
-            </div>
+        if let Some(err) = &self.input_error {
+            { self.view_error(ctx, err, None) }
+        }
+        {
+            self.connections.iter().filter_map(|(source, connection)| {
+                connection.fetch_error.as_ref().map(|err| self.view_error(ctx, err, Some(source)))
+            }).collect::<Html>()
         }
         <div class="chatBoxHeader">
             <div class="manualRefresh">
-                <button type="button" onclick={ctx.link().callback(|_| Msg::GetMessages)} style="width: fit-content; align-self: center;">
+                <button type="button" onclick={ctx.link().callback(|_| Msg::Refresh)} style="width: fit-content; align-self: center;">
                             { "Refresh" }
                             </button>
+                <button type="button" class={classes!(self.search_open.then_some("active"))} onclick={ctx.link().callback(|_| Msg::ToggleSearch)}>
+                            { "Search" }
+                            </button>
+                <button type="button" class={classes!(self.notification_settings_open.then_some("active"))} onclick={ctx.link().callback(|_| Msg::ToggleNotificationSettings)}>
+                            { "Notifications" }
+                            </button>
+                <button type="button" class={classes!(self.export_open.then_some("active"))} onclick={ctx.link().callback(|_| Msg::ToggleExport)}>
+                            { "Export" }
+                            </button>
+                <button type="button" class={classes!(self.history_settings_open.then_some("active"))} onclick={ctx.link().callback(|_| Msg::ToggleHistorySettings)}>
+                            { "History" }
+                            </button>
+                <button type="button" class={classes!(self.bridge_settings_open.then_some("active"))} onclick={ctx.link().callback(|_| Msg::ToggleBridgeSettings)}>
+                            { "Bridges" }
+                            </button>
             </div>
             <div class="refreshSettings">
                 <input type="checkbox" id="refresh" name="refresh" checked={self.state.refresh_enabled} onclick={ctx.link().callback(|_| Msg::ToggleRefresh) }/>
                 <label for="refresh">{ "Auto Refresh" }</label>
             </div>
         </div>
-        <div class="chatBoxContent" id="chatBoxContent">
+        if self.search_open { { self.view_search(ctx) } }
+        if self.notification_settings_open { { self.view_notification_settings(ctx) } }
+        if self.export_open { { self.view_export(ctx) } }
+        if self.history_settings_open { { self.view_history_settings(ctx) } }
+        if self.bridge_settings_open { { self.view_bridge_settings(ctx) } }
+        { self.view_tabs(ctx) }
+        <div class="chatBoxContent" id="chatBoxContent" ref={self.content_ref.clone()} onscroll={ctx.link().callback(|e: Event| {
+            e.target_dyn_into::<Element>().map_or(Msg::Nothing, |content| Msg::Scrolled {
+                offset: -f64::from(content.scroll_top()),
+                height: f64::from(content.client_height()),
+            })
+        })}>
+            <div key="spacerBelow" class="spacer" style={format!("height: {}px", window.below)} />
             {
-                html! {
-                    <>
-                    {
-                        self.messages.iter()
-                            .rev()
-                            .map(|msg| {
-                                let color = msg.chat_type.get_color();
-                                let uniq = format!("{}_{}", msg.timestamp, msg.sender_name);
-                                // todo: wrap message to next line. probably have div as float with wrapping and text set to fit content or smth?
-                                html!{
-                                    <>
-                                        <div key={uniq} class="chatEntry">
-                                            <div class="timestamp"> { format!("[{}]", msg.formatted_timestamp()) } </div>
-                                            <div class="chatType" style= { format!("color: {color}") }> { format!("[{}]", msg.chat_type) } </div>
-                                            if !msg.sender_name.is_empty() { <div class="sender" style= { format!("color: {color}") }> { format!("{}:", msg.sender_name) } </div> }
-                                            <span class="chatMessage" style= { format!("color: {color}") }>{ format!("{text}", text = msg.text) } </span>
-                                        </div>
-                                    </>
-                                }
-                            })
-                            .collect::<Html>()
-                    }
-                    </>
-                }
+                rows[window.start..window.end].iter().map(|row| match row {
+                    Row::Queued(msg) => self.view_queued(ctx, msg),
+                    Row::Message(msg) => self.view_message(ctx, msg, matcher),
+                }).collect::<Html>()
             }
+            <div key="spacerAbove" class="spacer" style={format!("height: {}px", window.above)} />
         </div>
+        if self.unseen > 0 {
+            <button type="button" class="newMessages" onclick={ctx.link().callback(|_| Msg::ScrollToNewest)}>
+                { format!("{} new message{} ↓", self.unseen, if self.unseen == 1 { "" } else { "s" }) }
+            </button>
+        }
         </>
         }
     }
 }
 
-pub mod models {
-    use std::fmt::Display;
-
-    use serde::{Deserialize, Serialize};
-    use web_sys::FormData;
-
-    #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, PartialOrd, Hash)]
-    #[serde(rename_all = "camelCase")]
-    #[repr(u32)]
-    pub enum ChatType {
-        None,
-        Debug,
-        Urgent,
-        Notice,
-        Say,
-        Shout,
-        Yell,
-        // syntax: /tell player name@world message
-        TellOutgoing,
-        TellIncoming,
-        Party,
-        FreeCompany,
-        Alliance,
-        CrossParty,
-        #[serde(rename = "ls1")]
-        LinkShell1,
-        #[serde(rename = "ls2")]
-        LinkShell2,
-        #[serde(rename = "ls3")]
-        LinkShell3,
-        #[serde(rename = "ls4")]
-        LinkShell4,
-        #[serde(rename = "ls5")]
-        LinkShell5,
-        #[serde(rename = "ls6")]
-        LinkShell6,
-        #[serde(rename = "ls7")]
-        LinkShell7,
-        #[serde(rename = "ls8")]
-        LinkShell8,
-        CrossLinkShell1,
-        CrossLinkShell2,
-        CrossLinkShell3,
-        CrossLinkShell4,
-        CrossLinkShell5,
-        CrossLinkShell6,
-        CrossLinkShell7,
-        CrossLinkShell8,
-        NoviceNetwork,
-        CustomEmote,
-        StandardEmote,
-        Echo,
-        SystemError,
-        SystemMessage,
-        ErrorMessage,
-        GatheringSystemMessage,
-        #[serde(rename = "npcDialogue")]
-        NPCDialogue,
-        #[serde(rename = "npcDialogueAnnouncements")]
-        NPCDialogueAnnouncements,
-        RetainerSale,
-        #[serde(untagged)]
-        Unimplemented(String),
-    }
-
-    #[derive(Deserialize, Hash)]
-    pub struct ChatMessage {
-        pub timestamp: String,
-        #[serde(rename = "type")]
-        pub chat_type: ChatType,
-        #[serde(rename = "senderName")]
-        pub sender_name: String,
-        pub text: String,
-    }
-
-    #[derive(Clone, Serialize)]
-    pub struct NewMessageRequest {
-        #[serde(rename = "type")]
-        pub chat_type: ChatType,
-        pub text: String,
-    }
-
-    impl TryFrom<FormData> for NewMessageRequest {
-        type Error = String;
-
-        fn try_from(value: FormData) -> Result<Self, Self::Error> {
-            let chat_type: ChatType = value
-                .get("chatType")
-                .as_string()
-                .ok_or_else(|| "Unable to get property for chatType".to_owned())
-                .and_then(|s| s.try_into())?;
-            let text = value
-                .get("text")
-                .as_string()
-                .ok_or_else(|| "Unable to get property for text".to_owned())?;
-            let me = Self { chat_type, text };
-
-            Ok(me)
-        }
-    }
-
-    impl TryFrom<String> for ChatType {
-        type Error = String;
-
-        fn try_from(value: String) -> Result<Self, Self::Error> {
-            Ok(match value.as_str() {
-                "Say" => ChatType::Say,
-                "Shout" => ChatType::Shout,
-                "Yell" => ChatType::Yell,
-                "Party" => ChatType::Party,
-                "FC" => ChatType::FreeCompany,
-                _ => return Err("Unsupported chatType".to_owned()),
+impl ChatBoxComponent {
+    /// Adds the messages received from `source`. Returns the ones that were not known yet, and
+    /// those of them newer than any message before, which are the ones that just arrived.
+    fn receive(
+        &mut self,
+        source: &Source,
+        mut received: Vec<ChatMessage>,
+    ) -> (Vec<ChatMessage>, Vec<ChatMessage>) {
+        for msg in &mut received {
+            msg.source.clone_from(source);
+        }
+        // bridges ignoring `since` send the messages the history removed again
+        if self.history.is_some() {
+            let now = chrono::Utc::now();
+            received.retain(|msg| self.history_settings.retains(msg, now, self.kept_since));
+        }
+        let newest = self.messages.last().and_then(ChatMessage::parsed_timestamp);
+        let added = insert_messages(&mut self.messages, received);
+        let new = added
+            .iter()
+            .filter(|msg| {
+                newest.is_none_or(|newest| msg.parsed_timestamp().is_some_and(|ts| ts > newest))
             })
+            .cloned()
+            .collect();
+        (added, new)
+    }
+
+    /// Updates everything derived from the message list. `new` are the messages that arrived
+    /// with the last update.
+    fn messages_changed(&mut self, ctx: &Context<Self>, new: &[ChatMessage]) {
+        if !new.is_empty() {
+            self.confirm_sent(new);
+            if !self.viewport.at_bottom() {
+                self.unseen += new.iter().filter(|msg| self.shows(msg)).count();
+            }
+            self.tabs.count_unread(new.iter().map(|msg| &msg.chat_type));
+            self.notifications
+                .notify(new.iter(), message_key, &ctx.link().callback(Msg::JumpTo));
         }
+        self.rows_changed();
+
+        let last_tell = self
+            .messages
+            .iter()
+            .rev()
+            .find(|msg| msg.chat_type == ChatType::TellIncoming);
+        let mut seen_channels = Vec::new();
+        for msg in &self.messages {
+            if !seen_channels.contains(&msg.chat_type) {
+                seen_channels.push(msg.chat_type.clone());
+            }
+        }
+        self.parent
+            .send_message(RootMsg::SetMessageInfo(MessageInfo {
+                last_tell: last_tell.and_then(|msg| TellTarget::from_sender(&msg.sender_name)),
+                last_tell_bridge: last_tell.and_then(|msg| msg.source.clone()),
+                seen_channels,
+            }));
     }
 
-    impl Display for ChatType {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            let str = match &self {
-                ChatType::None => "",
-                ChatType::Debug => "DBG",
-                ChatType::Urgent => "Urgent",
-                ChatType::Notice => "Notice",
-                ChatType::Say => "Say",
-                ChatType::Shout => "Shout",
-                ChatType::Yell => "Yell",
-                ChatType::TellOutgoing => "Tell>",
-                ChatType::TellIncoming => "Tell<",
-                ChatType::Party => "Party",
-                ChatType::FreeCompany => "FC",
-                ChatType::Alliance => "Alliance",
-                ChatType::CrossParty => "Party",
-                ChatType::LinkShell1 => "LS1",
-                ChatType::LinkShell2 => "LS2",
-                ChatType::LinkShell3 => "LS3",
-                ChatType::LinkShell4 => "LS4",
-                ChatType::LinkShell5 => "LS5",
-                ChatType::LinkShell6 => "LS6",
-                ChatType::LinkShell7 => "LS7",
-                ChatType::LinkShell8 => "LS8",
-                ChatType::CrossLinkShell1 => "CWLS1",
-                ChatType::CrossLinkShell2 => "CWLS2",
-                ChatType::CrossLinkShell3 => "CWLS3",
-                ChatType::CrossLinkShell4 => "CWLS4",
-                ChatType::CrossLinkShell5 => "CWLS5",
-                ChatType::CrossLinkShell6 => "CWLS6",
-                ChatType::CrossLinkShell7 => "CWLS7",
-                ChatType::CrossLinkShell8 => "CWLS8",
-                ChatType::NoviceNetwork => "NN",
-                ChatType::CustomEmote => "CEmote",
-                ChatType::StandardEmote => "Emote",
-                ChatType::Echo => "Echo",
-                ChatType::SystemError => "Error(System)",
-                ChatType::SystemMessage => "System",
-                ChatType::ErrorMessage => "Error",
-                ChatType::GatheringSystemMessage => "Gathering",
-                ChatType::NPCDialogue | ChatType::NPCDialogueAnnouncements => "NPC",
-                ChatType::RetainerSale => "Retainer",
-                ChatType::Unimplemented(val) => {
-                    log::warn!("Unknown chatType: {val}");
-                    "Unknown"
-                }
+    /// Tells the input form which bridges there are.
+    fn bridges_changed(&self) {
+        let bridges = self
+            .bridges
+            .sources()
+            .map(|source| {
+                let name = self.bridges.name(&source).to_owned();
+                (source, name)
+            })
+            .collect();
+        self.parent.send_message(RootMsg::SetBridges(bridges));
+    }
+
+    /// Stores the `added` messages in the history and applies its retention limits.
+    fn keep_history(&mut self, added: &[ChatMessage]) {
+        let Some(history) = self.history.clone() else {
+            return;
+        };
+        if !added.is_empty() {
+            let added = added.to_vec();
+            let history = history.clone();
+            update_history(async move { history.save(&added).await });
+        }
+
+        let expired = self
+            .history_settings
+            .expired(&self.messages, chrono::Utc::now());
+        if expired > 0 {
+            let expired = self.messages.drain(..expired).map(|msg| message_key(&msg));
+            self.viewport.forget(expired);
+            self.rows_changed();
+            self.kept_since = self
+                .messages
+                .first()
+                .and_then(ChatMessage::parsed_timestamp);
+            if let Some(oldest) = self.messages.first().cloned() {
+                update_history(async move { history.remove_before(&oldest).await });
+            }
+        }
+    }
+
+    /// The entries of the chat list from the bottom up. Queued messages are left out of search
+    /// results.
+    fn rows(&self) -> Vec<Row<'_>> {
+        let searching = self.search_open && self.matcher.is_some();
+        let queued = self
+            .outbox
+            .messages
+            .iter()
+            .rev()
+            .filter(|_| !searching)
+            .map(Row::Queued);
+        let received = self
+            .messages
+            .iter()
+            .rev()
+            .filter(|msg| self.shows(msg))
+            .map(Row::Message);
+        queued.chain(received).collect()
+    }
+
+    /// Called whenever the listed messages changed, by new, removed or queued messages or
+    /// another tab or search.
+    fn rows_changed(&mut self) {
+        self.row_keys = self.rows().iter().map(Row::key).collect();
+    }
+
+    /// Whether `msg` is listed, by the open search or else the active tab.
+    fn shows(&self, msg: &ChatMessage) -> bool {
+        match self.matcher.as_ref().filter(|_| self.search_open) {
+            Some(matcher) => matcher.matches(msg),
+            None => self.tabs.active_tab().shows(&msg.chat_type),
+        }
+    }
+
+    /// Sends the next queued message of every bridge, if nothing is on its way to it already.
+    fn flush_outbox(&mut self, ctx: &Context<Self>) {
+        for (source, connection) in &self.connections {
+            // kept until the bridge is added again with a valid token
+            if connection.unauthorized() {
+                continue;
+            }
+            let Some(msg) = self.outbox.next_to_send(source) else {
+                continue;
             };
-            write!(f, "{str}",)
+            msg.state = QueueState::Sending;
+            let id = msg.id;
+            let request = msg.request.clone();
+            let bridge = self.bridges.get(source).cloned();
+            ctx.link().send_future(async move {
+                let result = send_message(bridge.as_ref(), &request).await;
+                Msg::Sent { id, result }
+            });
+        }
+    }
+
+    /// Polls `source` after `delay`, unless auto refresh is off or a fetch is queued already.
+    fn queue_fetch(&self, ctx: &Context<Self>, source: &Source, delay: Duration) {
+        let Some(connection) = self.connections.get(source) else {
+            return;
+        };
+        if !self.state.refresh_enabled
+            || connection.unauthorized()
+            || connection.fetch_queued.swap(true, Ordering::Relaxed)
+        {
+            return;
+        }
+        let source = source.clone();
+        ctx.link().send_future(async move {
+            wasmtimer::tokio::sleep(delay).await;
+            Msg::GetMessages(source)
+        });
+    }
+
+    /// Removes the sent messages that were echoed by `received`.
+    fn confirm_sent(&mut self, received: &[ChatMessage]) {
+        let mut confirmed = false;
+        for msg in received {
+            confirmed |= self.outbox.confirm(msg);
+        }
+        if confirmed {
+            self.outbox.save();
+        }
+    }
+
+    /// Stops polling and streaming an additional bridge that rejected its token, retrying would
+    /// only fail again until the bridge is added again with a valid one.
+    fn bridge_unauthorized(&mut self, source: &Source, error: ErrorMessage) {
+        let Some(connection) = self.connections.get_mut(source) else {
+            return;
+        };
+        connection.stream.connection = None;
+        connection.stream.connected = false;
+        connection.fetch_error = Some(ErrorMessage {
+            description: "Access denied. Remove the bridge and add it again with a valid access \
+                token."
+                .to_owned(),
+            ..error
+        });
+    }
+
+    /// Called whenever a bridge answered, to send what was queued while it was unreachable.
+    fn bridge_up(&mut self, ctx: &Context<Self>, source: &Source) {
+        if self
+            .connections
+            .get_mut(source)
+            .is_some_and(|connection| std::mem::take(&mut connection.bridge_down))
+        {
+            self.flush_outbox(ctx);
+        }
+    }
+
+    /// An error with a button to copy its details. Errors of a bridge are prefixed with its
+    /// name while there are several.
+    fn view_error(&self, ctx: &Context<Self>, err: &ErrorMessage, source: Option<&Source>) -> Html {
+        let description = match source.filter(|_| self.bridges.several()) {
+            Some(source) => format!("{}: {}", self.bridges.name(source), err.description),
+            None => err.description.clone(),
+        };
+        let details = err.details.clone();
+        html! {
+            <div class="errorReport">
+                <span class="errorMessage">{ description }</span>
+                <button type="button" title="Copy details" onclick={ctx.link().callback(move |_| {
+                    if let Some(clipboard) = details
+                        .as_ref()
+                        .and_then(|_| web_sys::window())
+                        .and_then(|w| w.navigator().clipboard())
+                    {
+                        let _ = clipboard.write_text(details.as_deref().unwrap_or_default());
+                    }
+                    Msg::Nothing
+                })}>{ "📄" }</button>
+            </div>
+        }
+    }
+
+    /// Shows a submitted message at the end of the chat until the game echoes it.
+    fn view_queued(&self, ctx: &Context<Self>, msg: &QueuedMessage) -> Html {
+        let id = msg.id;
+        let chat_type = &msg.request.chat_type;
+        let color = chat_type.get_color();
+        let error = msg.error.as_deref().unwrap_or_default();
+        let state = match msg.state {
+            QueueState::Pending if msg.attempts == 0 => "queued".to_owned(),
+            QueueState::Pending => format!(
+                "waiting for the bridge, {} failed attempts: {error}",
+                msg.attempts
+            ),
+            QueueState::Sending => "sending…".to_owned(),
+            QueueState::Sent => "sent".to_owned(),
+            QueueState::Failed => format!("failed: {error}"),
+        };
+        let editable = matches!(msg.state, QueueState::Pending | QueueState::Failed);
+        html! {
+            <div key={format!("queued_{id}")} id={format!("queued_{id}")} class={classes!("chatEntry", "queued", (msg.state == QueueState::Failed).then_some("failed"))}>
+                <div class="timestamp"> { format!("[{}]", msg.formatted_submitted()) } </div>
+                if self.bridges.several() { <div class="source"> { format!("[{}]", self.bridges.name(&msg.bridge)) } </div> }
+                <div class="chatType" style={format!("color: {color}")}> { format!("[{chat_type}]") } </div>
+                if let Some(target) = &msg.request.target { <div class="sender" style={format!("color: {color}")}> { format!("{target}:") } </div> }
+                <span class="chatMessage" style={format!("color: {color}")}>{ msg.request.text.clone() } </span>
+                <span class="queueState">{ state }</span>
+                if editable {
+                    <button type="button" onclick={ctx.link().callback(move |_| Msg::Outbox(OutboxAction::Resend(id)))}>{ "Resend" }</button>
+                    <button type="button" onclick={ctx.link().callback(move |_| Msg::Outbox(OutboxAction::Edit(id)))}>{ "Edit" }</button>
+                    <button type="button" onclick={ctx.link().callback(move |_| Msg::Outbox(OutboxAction::Discard(id)))}>{ "Discard" }</button>
+                }
+            </div>
         }
     }
 
-    impl ChatType {
-        pub fn get_color(&self) -> String {
-            match self {
-                Self::Yell => "#fcfc03",
-                Self::Shout => "#ffce63",
-                Self::TellIncoming | Self::TellOutgoing => "#f263ff",
-                Self::Alliance => "#ed9511",
-                Self::FreeCompany => "#4ef542",
-                Self::Party => "#426ff5",
-                cwl if (Self::CrossLinkShell1..=Self::CrossLinkShell8).contains(cwl) => "#9f3cbd",
-                Self::NoviceNetwork => "#cfe05c",
-                ls if (Self::LinkShell1..=Self::LinkShell8).contains(ls) => "#fad2b9",
-                Self::StandardEmote | Self::CustomEmote => "#e1faf9",
-                Self::NPCDialogue | Self::NPCDialogueAnnouncements => "#6ead10",
-                _ => "#FFFFFFFF",
+    fn view_message(
+        &self,
+        ctx: &Context<Self>,
+        msg: &ChatMessage,
+        matcher: Option<&Matcher>,
+    ) -> Html {
+        let color = msg.chat_type.get_color();
+        let uniq = message_key(msg);
+        let is_jump_target = self.jump_target.as_ref() == Some(&uniq);
+        let text = match (shown_payloads(msg), matcher) {
+            (Some(payloads), _) => view_payloads(payloads, matcher),
+            (_, Some(matcher)) => highlight(&msg.text, &matcher.highlights(&msg.text)),
+            (_, None) => html! { msg.text.clone() },
+        };
+        let onclick = matcher.map(|_| {
+            let key = uniq.clone();
+            ctx.link().callback(move |_| Msg::JumpTo(key.clone()))
+        });
+        // todo: wrap message to next line. probably have div as float with wrapping and text set to fit content or smth?
+        html! {
+            <div key={uniq.clone()} id={uniq} onclick={onclick}
+                class={classes!("chatEntry", matcher.is_some().then_some("searchResult"), is_jump_target.then_some("jumpTarget"))}>
+                <div class="timestamp"> { format!("[{}]", msg.formatted_timestamp()) } </div>
+                if self.bridges.several() { <div class="source"> { format!("[{}]", self.bridges.name(&msg.source)) } </div> }
+                <div class="chatType" style= { format!("color: {color}") }> { format!("[{}]", msg.chat_type) } </div>
+                if !msg.sender_name.is_empty() { <div class="sender" style= { format!("color: {color}") }> { format!("{}:", msg.sender_name) } </div> }
+                <span class="chatMessage" style= { format!("color: {color}") }>{ text } </span>
+            </div>
+        }
+    }
+
+    fn view_search(&self, ctx: &Context<Self>) -> Html {
+        let input_callback = |update: fn(String) -> SearchUpdate| {
+            ctx.link().callback(move |e: InputEvent| {
+                let value = e
+                    .target_dyn_into::<HtmlInputElement>()
+                    .map(|input| input.value())
+                    .unwrap_or_default();
+                Msg::Search(update(value))
+            })
+        };
+        let matches = self.matcher.as_ref().map(|matcher| {
+            self.messages
+                .iter()
+                .filter(|msg| matcher.matches(msg))
+                .count()
+        });
+        html! {
+            <div class="searchBar">
+                <input type="search" placeholder="Search messages" value={self.search.text.clone()}
+                    oninput={input_callback(SearchUpdate::Text)} />
+                <label>
+                    <input type="checkbox" checked={self.search.regex} onclick={ctx.link().callback({
+                        let regex = self.search.regex;
+                        move |_| Msg::Search(SearchUpdate::Regex(!regex))
+                    })} />
+                    { "Regex" }
+                </label>
+                <input type="text" placeholder="Sender" value={self.search.sender.clone()}
+                    oninput={input_callback(SearchUpdate::Sender)} />
+                <select onchange={ctx.link().callback(|e: Event| {
+                    let channel = e
+                        .target_dyn_into::<HtmlSelectElement>()
+                        .and_then(|select| select.value().parse::<usize>().ok())
+                        .and_then(|index| FILTER_CHANNELS.get(index).cloned());
+                    Msg::Search(SearchUpdate::Channel(channel))
+                })}>
+                    <option value="" selected={self.search.channel.is_none()}>{ "Any channel" }</option>
+                    {
+                        FILTER_CHANNELS.iter().enumerate().map(|(index, chat_type)| html! {
+                            <option value={index.to_string()} selected={self.search.channel.as_ref() == Some(chat_type)}>
+                                { format!("{chat_type:?}") }
+                            </option>
+                        }).collect::<Html>()
+                    }
+                </select>
+                <input type="datetime-local" title="From" value={self.search.from.clone()}
+                    oninput={input_callback(SearchUpdate::From)} />
+                <input type="datetime-local" title="To" value={self.search.to.clone()}
+                    oninput={input_callback(SearchUpdate::To)} />
+                if let Some(error) = &self.search_error {
+                    <span class="errorMessage">{ error.clone() }</span>
+                } else if let Some(matches) = matches {
+                    <span class="matches">{ format!("{matches} matches") }</span>
+                }
+            </div>
+        }
+    }
+
+    fn view_notification_settings(&self, ctx: &Context<Self>) -> Html {
+        let settings = &self.notifications;
+        let permission = notifications::permission();
+        let hint = match permission {
+            None => Some("Notifications are not supported by this browser"),
+            Some(web_sys::NotificationPermission::Denied) => {
+                Some("Notifications are blocked in the browser's site settings")
             }
-            .to_owned()
+            _ => None,
+        };
+        html! {
+            <div class="notificationSettings">
+                <div class="notificationOptions">
+                    <label>
+                        <input type="checkbox" checked={settings.enabled} disabled={hint.is_some()}
+                            onclick={ctx.link().callback({
+                                let enabled = settings.enabled;
+                                move |_| Msg::Notifications(NotificationUpdate::Enabled(!enabled))
+                            })} />
+                        { "Notify while in background" }
+                    </label>
+                    <input type="text" placeholder="Keywords, comma separated" value={settings.keywords.join(", ")}
+                        onchange={ctx.link().callback(|e: Event| {
+                            let value = e
+                                .target_dyn_into::<HtmlInputElement>()
+                                .map(|input| input.value())
+                                .unwrap_or_default();
+                            Msg::Notifications(NotificationUpdate::Keywords(value))
+                        })} />
+                    if let Some(hint) = hint {
+                        <span class="errorMessage">{ hint }</span>
+                    }
+                </div>
+                <div class="tabChannels">
+                    {
+                        FILTER_CHANNELS.iter().map(|chat_type| {
+                            let id = format!("notifyChannel{chat_type:?}");
+                            let toggled = chat_type.clone();
+                            html! {
+                                <div class="tabChannel">
+                                    <input type="checkbox" id={id.clone()} checked={settings.channels.contains(chat_type)}
+                                        onclick={ctx.link().callback(move |_| Msg::Notifications(NotificationUpdate::ToggleChannel(toggled.clone())))} />
+                                    <label for={id} style={format!("color: {}", chat_type.get_color())}>{ format!("{chat_type:?}") }</label>
+                                </div>
+                            }
+                        }).collect::<Html>()
+                    }
+                </div>
+            </div>
         }
     }
 
-    impl ChatMessage {
-        pub fn formatted_timestamp(&self) -> String {
-            if let Ok(date_time) = self.timestamp.parse::<chrono::DateTime<chrono::Utc>>() {
-                chrono::DateTime::<chrono::Local>::from(date_time)
-                    .format("%Y-%m-%d %H:%M")
-                    .to_string()
-            } else {
-                "N/A".to_owned()
+    fn view_export(&self, ctx: &Context<Self>) -> Html {
+        let filtered = self.export.filtered;
+        html! {
+            <div class="exportSettings">
+                <select onchange={ctx.link().callback(|e: Event| {
+                    let format = e
+                        .target_dyn_into::<HtmlSelectElement>()
+                        .and_then(|select| select.value().parse::<usize>().ok())
+                        .and_then(|index| ExportFormat::ALL.get(index).copied())
+                        .unwrap_or(ExportFormat::Text);
+                    Msg::Export(ExportUpdate::Format(format))
+                })}>
+                    {
+                        ExportFormat::ALL.iter().enumerate().map(|(index, format)| html! {
+                            <option value={index.to_string()} selected={*format == self.export.format}>
+                                { format.label() }
+                            </option>
+                        }).collect::<Html>()
+                    }
+                </select>
+                <label>
+                    <input type="checkbox" checked={filtered}
+                        onclick={ctx.link().callback(move |_| Msg::Export(ExportUpdate::Filtered(!filtered)))} />
+                    { "Only shown messages" }
+                </label>
+                <button type="button" onclick={ctx.link().callback(|_| Msg::Download)}>{ "Download" }</button>
+            </div>
+        }
+    }
+
+    fn view_history_settings(&self, ctx: &Context<Self>) -> Html {
+        let settings = &self.history_settings;
+        let number_callback = |update: fn(&str) -> Option<HistoryUpdate>| {
+            ctx.link().callback(move |e: Event| {
+                e.target_dyn_into::<HtmlInputElement>()
+                    .and_then(|input| update(&input.value()))
+                    .map_or(Msg::Nothing, Msg::History)
+            })
+        };
+        html! {
+            <div class="historySettings">
+                <div class="historyOptions">
+                    <label>
+                        <input type="checkbox" checked={settings.enabled}
+                            onclick={ctx.link().callback({
+                                let enabled = settings.enabled;
+                                move |_| Msg::History(HistoryUpdate::Enabled(!enabled))
+                            })} />
+                        { "Keep history on this device" }
+                    </label>
+                    <label>
+                        { "Days" }
+                        <input type="number" min="0" title="0 keeps messages regardless of age" disabled={!settings.enabled}
+                            value={settings.max_days.to_string()}
+                            onchange={number_callback(|value| value.parse().ok().map(HistoryUpdate::MaxDays))} />
+                    </label>
+                    <label>
+                        { "Messages" }
+                        <input type="number" min="1" disabled={!settings.enabled}
+                            value={settings.max_messages.to_string()}
+                            onchange={number_callback(|value| {
+                                value.parse().ok().filter(|max| *max > 0).map(HistoryUpdate::MaxMessages)
+                            })} />
+                    </label>
+                    <button type="button" disabled={self.history.is_none()} onclick={ctx.link().callback(|_| Msg::ClearHistory)}>
+                        { "Clear local history" }
+                    </button>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_bridge_settings(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <div class="bridgeSettings">
+                <div class="bridgeOptions">
+                    <label>
+                        { "This bridge" }
+                        <input type="text" value={self.bridges.primary_name.clone()}
+                            onchange={ctx.link().callback(|e: Event| {
+                                let value = e
+                                    .target_dyn_into::<HtmlInputElement>()
+                                    .map(|input| input.value())
+                                    .unwrap_or_default();
+                                Msg::Bridges(BridgeUpdate::PrimaryName(value))
+                            })} />
+                    </label>
+                    <label>
+                        { "URL" }
+                        <input type="text" placeholder="Next to this page" title="Where the bridge serving this page is reached, e.g. when the page is hosted elsewhere"
+                            value={self.bridges.primary_url.clone().unwrap_or_default()}
+                            onchange={ctx.link().callback(|e: Event| {
+                                let value = e
+                                    .target_dyn_into::<HtmlInputElement>()
+                                    .map(|input| input.value())
+                                    .unwrap_or_default();
+                                Msg::Bridges(BridgeUpdate::PrimaryUrl(value))
+                            })} />
+                    </label>
+                </div>
+                {
+                    self.bridges.bridges.iter().map(|bridge| {
+                        let name = bridge.name.clone();
+                        html! {
+                            <div class="bridgeOptions">
+                                <span class="bridgeName">{ bridge.name.clone() }</span>
+                                <span class="bridgeUrl">{ bridge.url.clone() }</span>
+                                <button type="button" onclick={ctx.link().callback(move |_| Msg::Bridges(BridgeUpdate::Remove(name.clone())))}>
+                                    { "Remove" }
+                                </button>
+                            </div>
+                        }
+                    }).collect::<Html>()
+                }
+                <form class="bridgeOptions" onsubmit={ctx.link().batch_callback(|e: SubmitEvent| {
+                    e.prevent_default();
+                    let form = e.target_dyn_into::<HtmlFormElement>()?;
+                    let data = FormData::new_with_form(&form).ok()?;
+                    let field = |name: &str| data.get(name).as_string().unwrap_or_default();
+                    let bridge = Bridge {
+                        name: field("name"),
+                        url: field("url"),
+                        token: Some(field("token")),
+                    };
+                    form.reset();
+                    Some(Msg::Bridges(BridgeUpdate::Add(bridge)))
+                })}>
+                    <input type="text" name="name" placeholder="Name" required=true />
+                    <input type="url" name="url" placeholder="http://192.168.0.2:9876" required=true />
+                    <input type="password" name="token" placeholder="Access token" autocomplete="off" />
+                    <button type="submit">{ "Add bridge" }</button>
+                </form>
+                if let Some(error) = &self.bridge_error {
+                    <span class="errorMessage">{ error.clone() }</span>
+                }
+            </div>
+        }
+    }
+
+    fn view_tabs(&self, ctx: &Context<Self>) -> Html {
+        let active = self.tabs.active;
+        let active_tab = self.tabs.active_tab();
+        html! {
+        <>
+        <div class="chatTabs">
+            {
+                self.tabs.tabs.iter().enumerate().map(|(index, tab)| html! {
+                    <button type="button" class={classes!("tab", (index == active).then_some("active"))}
+                        onclick={ctx.link().callback(move |_| Msg::SelectTab(index))}>
+                        { tab.name.clone() }
+                        if tab.unread > 0 { <span class="unread">{ tab.unread }</span> }
+                    </button>
+                }).collect::<Html>()
             }
+            <button type="button" class="tab" title="Add tab" onclick={ctx.link().callback(|_| Msg::AddTab)}>{ "+" }</button>
+            <button type="button" class={classes!("tab", self.editing_tab.then_some("active"))} title="Edit tab"
+                onclick={ctx.link().callback(|_| Msg::ToggleTabEditor)}>{ "⚙" }</button>
+        </div>
+        if self.editing_tab {
+            <div class="tabEditor">
+                <div class="tabName">
+                    <input type="text" value={active_tab.name.clone()} oninput={ctx.link().callback(|e: InputEvent| {
+                        let value = e
+                            .target_dyn_into::<HtmlInputElement>()
+                            .map(|input| input.value())
+                            .unwrap_or_default();
+                        Msg::RenameTab(value)
+                    })} />
+                    <button type="button" disabled={self.tabs.tabs.len() <= 1}
+                        onclick={ctx.link().callback(move |_| Msg::RemoveTab(active))}>{ "Delete Tab" }</button>
+                </div>
+                <div class="tabChannels">
+                    {
+                        FILTER_CHANNELS.iter().map(|chat_type| {
+                            let id = format!("tabChannel{chat_type:?}");
+                            let toggled = chat_type.clone();
+                            html! {
+                                <div class="tabChannel">
+                                    <input type="checkbox" id={id.clone()} checked={active_tab.channels.contains(chat_type)}
+                                        onclick={ctx.link().callback(move |_| Msg::ToggleTabChannel(toggled.clone()))} />
+                                    <label for={id} style={format!("color: {}", chat_type.get_color())}>{ format!("{chat_type:?}") }</label>
+                                </div>
+                            }
+                        }).collect::<Html>()
+                    }
+                </div>
+            </div>
         }
+        </>
+        }
+    }
+}
+
+/// An entry of the chat list.
+enum Row<'a> {
+    Queued(&'a QueuedMessage),
+    Message(&'a ChatMessage),
+}
+
+impl Row<'_> {
+    fn key(&self) -> String {
+        match self {
+            Row::Queued(msg) => format!("queued_{}", msg.id),
+            Row::Message(msg) => message_key(msg),
+        }
+    }
+}
+
+fn open_history(ctx: &Context<ChatBoxComponent>) {
+    ctx.link().send_future(async {
+        Msg::HistoryOpened(History::open().await.map_err(|e| format!("{e:?}")))
+    });
+}
+
+/// Runs a history update in the background. Failures are only logged, the chat itself works
+/// without the history.
+fn update_history(update: impl std::future::Future<Output = Result<(), JsValue>> + 'static) {
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(e) = update.await {
+            log::error!("Unable to update message history: {e:?}");
+        }
+    });
+}
+
+fn wait_for_echo(ctx: &Context<ChatBoxComponent>, id: u64) {
+    ctx.link().send_future(async move {
+        wasmtimer::tokio::sleep(std::time::Duration::from_secs(ECHO_TIMEOUT_SEC)).await;
+        Msg::EchoTimeout(id)
+    });
+}
+
+/// Key and element id of a message, also used to jump to it.
+fn message_key(msg: &ChatMessage) -> String {
+    msg.id().to_string()
+}
+
+/// Moves the keyboard focus to the input with the given id.
+fn focus(id: &str) {
+    if let Some(input) = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|doc| doc.get_element_by_id(id))
+        .and_then(|elem| elem.dyn_into::<HtmlInputElement>().ok())
+    {
+        let _ = input.focus();
+    }
+}
+
+/// Switches the input form to the channel a tab is mostly about.
+fn select_primary_channel(parent: &Scope<App>, tab: &ChatTab) {
+    let value = tab.primary_channel().and_then(|primary| {
+        INPUT_CHANNELS
+            .iter()
+            .find(|(_, chat_type, _)| chat_type == primary)
+    });
+    if let Some((value, _, _)) = value {
+        parent.send_message(RootMsg::SetChatType((*value).to_owned()));
     }
 }
 
@@ -472,9 +2169,12 @@ pub mod requests {
 
     use serde::de;
 
+    use std::sync::RwLock;
+
     use super::{
+        bridges::Bridge,
         models::{ChatMessage, NewMessageRequest},
-        ErrorMessage,
+        ErrorKind, ErrorMessage,
     };
 
     lazy_static::lazy_static!(
@@ -491,34 +2191,74 @@ pub mod requests {
         };
     );
 
+    static TOKEN: RwLock<Option<String>> = RwLock::new(None);
+    static BASE_URL: RwLock<Option<String>> = RwLock::new(None);
+
     const FALLBACK_URL: &str = "http://localhost:9876";
     const MESSAGES_URI: &str = "/messages";
 
-    pub async fn get_messages() -> Result<Vec<ChatMessage>, ErrorMessage> {
-        let response = CLIENT
-            .clone()
-            .get(url(MESSAGES_URI)?)
-            .send()
-            .await
-            .map_err(|e| {
-                log::error!(
-                    "{:?} failed. Caused By: {}",
-                    e.url(),
-                    e.source().map_or_else(|| String::new(), |s| s.to_string())
-                );
-                ErrorMessage {
-                    description: "Unable to get messages from Server. Make sure it is running!"
-                        .to_owned(),
-                    details: e.source().map(|source| source.to_string()),
-                }
-            })?;
+    /// Sets the access token sent with every request to the bridge serving the page.
+    pub fn set_token(token: Option<String>) {
+        *TOKEN.write().expect("token lock poisoned") = token;
+    }
+
+    /// The access token for `bridge`, or the bridge serving the page if `None`.
+    pub fn token(bridge: Option<&Bridge>) -> Option<String> {
+        match bridge {
+            Some(bridge) => bridge.token.clone(),
+            None => TOKEN.read().expect("token lock poisoned").clone(),
+        }
+    }
+
+    fn authorize(
+        request: reqwest_wasm::RequestBuilder,
+        bridge: Option<&Bridge>,
+    ) -> reqwest_wasm::RequestBuilder {
+        match token(bridge) {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    fn unauthorized() -> ErrorMessage {
+        ErrorMessage {
+            kind: ErrorKind::Unauthorized,
+            description: "Access denied. Please log in with a valid access token.".to_owned(),
+            details: None,
+        }
+    }
+
+    /// Fetches the message backlog. With a `since` timestamp only newer messages are requested,
+    /// but servers without cursor support will still return everything.
+    pub async fn get_messages(
+        bridge: Option<&Bridge>,
+        since: Option<&str>,
+    ) -> Result<Vec<ChatMessage>, ErrorMessage> {
+        let mut request = authorize(CLIENT.clone().get(url(bridge, MESSAGES_URI)?), bridge);
+        if let Some(since) = since {
+            request = request.query(&[("since", since)]);
+        }
+        let response = request.send().await.map_err(|e| {
+            log::error!(
+                "{:?} failed. Caused By: {}",
+                e.url(),
+                e.source().map_or_else(String::new, |s| s.to_string())
+            );
+            ErrorMessage {
+                kind: ErrorKind::Connection,
+                description: "Unable to get messages from Server. Make sure it is running!"
+                    .to_owned(),
+                details: e.source().map(|source| source.to_string()),
+            }
+        })?;
         from_response::<Vec<ChatMessage>>(response).await
     }
 
-    pub async fn send_message(msg: &NewMessageRequest) -> Result<(), ErrorMessage> {
-        let response = CLIENT
-            .clone()
-            .post(url(MESSAGES_URI)?)
+    pub async fn send_message(
+        bridge: Option<&Bridge>,
+        msg: &NewMessageRequest,
+    ) -> Result<(), ErrorMessage> {
+        let response = authorize(CLIENT.clone().post(url(bridge, MESSAGES_URI)?), bridge)
             .json(msg)
             .send()
             .await
@@ -526,9 +2266,10 @@ pub mod requests {
                 log::error!(
                     "{:?} failed. Caused By: {}",
                     e.url(),
-                    e.source().map_or_else(|| String::new(), |s| s.to_string())
+                    e.source().map_or_else(String::new, |s| s.to_string())
                 );
                 ErrorMessage {
+                    kind: ErrorKind::Connection,
                     description: "Unable to send message to Server. Make sure it is running!"
                         .to_owned(),
                     details: e.source().map(|source| source.to_string()),
@@ -537,14 +2278,14 @@ pub mod requests {
 
         match response.status().as_u16() {
             200..=299 => Ok(()),
+            401 | 403 => Err(unauthorized()),
             400 => Err(ErrorMessage {
-                description: format!(
-                    "Bad request: {}",
-                    response.text().await.unwrap_or_else(|_| String::new())
-                ),
+                kind: ErrorKind::Rejected,
+                description: format!("Bad request: {}", response.text().await.unwrap_or_default()),
                 details: None,
             }),
             unknown_code => Err(ErrorMessage {
+                kind: ErrorKind::Http,
                 description: format!("unexpected response: {}", unknown_code),
                 details: None,
             }),
@@ -557,8 +2298,10 @@ pub mod requests {
     {
         match value.status().as_u16() {
             200..=299 => {}
+            401 | 403 => return Err(unauthorized()),
             _ => {
                 return Err(ErrorMessage {
+                    kind: ErrorKind::Http,
                     description: format!("HTTP Error: {error_code}", error_code = value.status()),
                     details: value.text().await.ok(),
                 })
@@ -566,68 +2309,138 @@ pub mod requests {
         }
 
         let bytes = value.text().await.map_err(|e| ErrorMessage {
+            kind: ErrorKind::Parse,
             description: "Unable to read response".to_owned(),
             details: Some(e.to_string()),
         })?;
 
         serde_json::from_str(&bytes).map_err(|e| ErrorMessage {
+            kind: ErrorKind::Parse,
             description: "JSON parsing failed".to_owned(),
             details: Some(e.to_string()),
         })
     }
 
-    #[cfg(feature = "devtest")]
-    fn url(uri: &str) -> Result<reqwest_wasm::Url, ErrorMessage> {
-        let url = format!("{FALLBACK_URL}{uri}");
-        reqwest_wasm::Url::parse(&url).map_err(|e| ErrorMessage {
-            description: "Unable to parse URL".to_owned(),
-            details: Some(e.to_string()),
+    /// The URL of the endpoint `uri` of `bridge`, or of the bridge serving the page if `None`.
+    pub(super) fn url(
+        bridge: Option<&Bridge>,
+        uri: &str,
+    ) -> Result<reqwest_wasm::Url, ErrorMessage> {
+        let base = match bridge {
+            Some(bridge) => bridge.url.clone(),
+            None => base_url().unwrap_or_default(),
+        };
+        endpoint(&page_url(), &base, uri).map_err(|e| ErrorMessage {
+            kind: ErrorKind::Connection,
+            description: format!("Unable to parse URL {base}"),
+            details: Some(e),
         })
     }
 
-    #[cfg(not(feature = "devtest"))]
-    fn url(uri: &str) -> Result<reqwest_wasm::Url, ErrorMessage> {
-        let url = format!(
-            "{}{uri}",
-            web_sys::window()
-                .and_then(|w| w.document())
-                .and_then(|doc| doc.location())
-                .and_then(|loc| loc.href().ok())
-                .and_then(|href| Some(if href.ends_with('/') {
-                    href[0..href.len() - 1].to_string()
-                } else {
-                    href
-                }))
-                .unwrap_or_else(|| {
-                    log::error!("Unable to get baseurl from browser..trying fallback");
-                    FALLBACK_URL.to_owned()
-                })
-        );
+    /// Resolves the endpoint `uri` of a bridge at `base`, which may be relative to the page at
+    /// `page`. Query and fragment are ignored, as is a file name at the end of the path, so the
+    /// endpoints are found next to the page even when it is hosted under a path prefix.
+    pub fn endpoint(page: &str, base: &str, uri: &str) -> Result<reqwest_wasm::Url, String> {
+        let page = reqwest_wasm::Url::parse(page).map_err(|e| e.to_string())?;
+        let base = page.join(base).map_err(|e| e.to_string())?;
+        directory(base)
+            .join(uri.trim_start_matches('/'))
+            .map_err(|e| e.to_string())
+    }
 
-        reqwest_wasm::Url::parse(&url).map_err(|e| ErrorMessage {
-            description: "Unable to parse URL".to_owned(),
-            details: Some(e.to_string()),
-        })
+    fn directory(mut url: reqwest_wasm::Url) -> reqwest_wasm::Url {
+        url.set_query(None);
+        url.set_fragment(None);
+        let path = url.path().to_owned();
+        if !path.ends_with('/') {
+            let (dir, file) = path.rsplit_once('/').unwrap_or_default();
+            if file.contains('.') {
+                url.set_path(&format!("{dir}/"));
+            } else {
+                url.set_path(&format!("{path}/"));
+            }
+        }
+        url
+    }
+
+    /// Where the bridge serving the page is reached, instead of next to the page.
+    pub fn set_base_url(url: Option<String>) {
+        *BASE_URL.write().expect("base url lock poisoned") = url;
+    }
+
+    fn base_url() -> Option<String> {
+        BASE_URL.read().expect("base url lock poisoned").clone()
+    }
+
+    #[cfg(feature = "devtest")]
+    fn page_url() -> String {
+        FALLBACK_URL.to_owned()
+    }
+
+    /// The URL relative paths are resolved against, which honors a `<base>` tag.
+    #[cfg(not(feature = "devtest"))]
+    fn page_url() -> String {
+        web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|doc| doc.base_uri().ok().flatten())
+            .unwrap_or_else(|| {
+                log::error!("Unable to get baseurl from browser..trying fallback");
+                FALLBACK_URL.to_owned()
+            })
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::models::{ChatMessage, ChatType};
+    use super::models::ChatType;
 
-    const timestamp: &str = "2024-07-14T10:18:02.8379913+02:00";
+    #[test]
+    fn test_input_channels() {
+        for (value, chat_type, _) in super::INPUT_CHANNELS {
+            assert_eq!(
+                ChatType::try_from(value.to_string()).as_ref(),
+                Ok(chat_type)
+            );
+            assert!(
+                chat_type.input_channel().is_some(),
+                "{value} is not sendable"
+            );
+        }
+    }
 
     #[test]
-    fn test_timestamp() {
-        let message = ChatMessage {
-            timestamp: timestamp.to_owned(),
-            chat_type: ChatType::Say,
-            sender_name: "none".to_owned(),
-            text: "test".to_owned(),
-        };
+    fn test_endpoint() {
+        use super::requests::endpoint;
 
-        let formatted = message.formatted_timestamp();
-        println!("{formatted}");
-        assert_eq!(formatted, "2024-07-14 10:18")
+        let url = |page: &str, base: &str| endpoint(page, base, "/messages").unwrap().to_string();
+        assert_eq!(
+            url("http://host:9876/?tab=fc", ""),
+            "http://host:9876/messages"
+        );
+        assert_eq!(url("http://host:9876/#x", ""), "http://host:9876/messages");
+        // hosted under a path prefix
+        assert_eq!(
+            url("https://host/xivchat/index.html?a=1", ""),
+            "https://host/xivchat/messages"
+        );
+        assert_eq!(
+            url("https://host/xivchat", ""),
+            "https://host/xivchat/messages"
+        );
+        // overridden
+        assert_eq!(
+            url("https://host/xivchat/", "http://10.0.0.2:9876"),
+            "http://10.0.0.2:9876/messages"
+        );
+        assert_eq!(
+            url("https://host/xivchat/", "/bridge"),
+            "https://host/bridge/messages"
+        );
+        assert_eq!(
+            endpoint("https://host/", "", "/messages/stream")
+                .unwrap()
+                .as_str(),
+            "https://host/messages/stream"
+        );
     }
 }
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::storage;
+
+const STORAGE_KEY: &str = "bridges";
+/// Access tokens by bridge name, while they are only kept for the browser session.
+const SESSION_TOKENS_KEY: &str = "bridge_tokens";
+
+/// Identifies where a message came from or is sent through: `None` for the bridge serving this
+/// page, otherwise the name of an additional one.
+pub type Source = Option<String>;
+
+/// An additional bridge, e.g. the one of a second game client.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Bridge {
+    pub name: String,
+    /// Base URL the bridge's endpoints are appended to.
+    pub url: String,
+    pub token: Option<String>,
+}
+
+/// The bridges messages are received from and sent through, besides the one serving the page.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct BridgeSettings {
+    /// Shown for the bridge serving the page once there are others.
+    pub primary_name: String,
+    /// Where the bridge serving the page is reached instead of next to the page, e.g. when
+    /// the page is hosted elsewhere. May be relative to the page.
+    pub primary_url: Option<String>,
+    pub bridges: Vec<Bridge>,
+    /// Whether the access tokens are kept in local storage, like the login's when it is
+    /// remembered. Otherwise they are gone once the tab is closed.
+    #[serde(skip)]
+    remember_tokens: bool,
+}
+
+pub enum BridgeUpdate {
+    PrimaryName(String),
+    /// Empty to reach the bridge next to the page again.
+    PrimaryUrl(String),
+    Add(Bridge),
+    Remove(String),
+}
+
+impl Default for BridgeSettings {
+    fn default() -> Self {
+        Self {
+            primary_name: "Main".to_owned(),
+            primary_url: None,
+            bridges: Vec::new(),
+            remember_tokens: true,
+        }
+    }
+}
+
+impl BridgeSettings {
+    pub fn load(remember_tokens: bool) -> Self {
+        let mut settings = storage::load::<Self>(STORAGE_KEY).unwrap_or_default();
+        let tokens = storage::load_session::<HashMap<String, String>>(SESSION_TOKENS_KEY)
+            .unwrap_or_default();
+        for bridge in &mut settings.bridges {
+            if bridge.token.is_none() {
+                bridge.token = tokens.get(&bridge.name).cloned();
+            }
+        }
+        settings.remember_tokens = remember_tokens;
+        // moves the tokens to where they are kept now
+        settings.save();
+        settings
+    }
+
+    fn save(&self) {
+        if self.remember_tokens {
+            storage::save(STORAGE_KEY, self);
+            storage::remove_session(SESSION_TOKENS_KEY);
+            return;
+        }
+        let tokens: HashMap<&str, &str> = self
+            .bridges
+            .iter()
+            .filter_map(|bridge| Some((bridge.name.as_str(), bridge.token.as_deref()?)))
+            .collect();
+        storage::save_session(SESSION_TOKENS_KEY, &tokens);
+        let without_tokens = Self {
+            primary_name: self.primary_name.clone(),
+            primary_url: self.primary_url.clone(),
+            bridges: self
+                .bridges
+                .iter()
+                .map(|bridge| Bridge {
+                    token: None,
+                    ..bridge.clone()
+                })
+                .collect(),
+            remember_tokens: false,
+        };
+        storage::save(STORAGE_KEY, &without_tokens);
+    }
+
+    /// Checks and applies `update`, nothing is changed if it is invalid.
+    pub fn apply(&mut self, update: BridgeUpdate) -> Result<(), String> {
+        match update {
+            BridgeUpdate::PrimaryName(name) => {
+                let name = name.trim();
+                if name.is_empty() {
+                    return Err("The name must not be empty".to_owned());
+                }
+                if self.get(&Some(name.to_owned())).is_some() {
+                    return Err(format!("There already is a bridge named {name}"));
+                }
+                self.primary_name = name.to_owned();
+            }
+            BridgeUpdate::PrimaryUrl(url) => {
+                let url = url.trim();
+                self.primary_url = (!url.is_empty()).then(|| url.to_owned());
+            }
+            BridgeUpdate::Add(mut bridge) => {
+                bridge.name = bridge.name.trim().to_owned();
+                bridge.url = bridge.url.trim().trim_end_matches('/').to_owned();
+                bridge.token = bridge.token.filter(|token| !token.is_empty());
+                if bridge.name.is_empty() {
+                    return Err("The name must not be empty".to_owned());
+                }
+                if bridge.name == self.primary_name
+                    || self.get(&Some(bridge.name.clone())).is_some()
+                {
+                    return Err(format!("There already is a bridge named {}", bridge.name));
+                }
+                match reqwest_wasm::Url::parse(&bridge.url) {
+                    Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+                    _ => return Err(format!("{} is not an http(s) URL", bridge.url)),
+                }
+                self.bridges.push(bridge);
+            }
+            BridgeUpdate::Remove(name) => self.bridges.retain(|bridge| bridge.name != name),
+        }
+        self.save();
+        Ok(())
+    }
+
+    /// The additional bridge `source` refers to, `None` for the one serving the page or one
+    /// that was removed.
+    pub fn get(&self, source: &Source) -> Option<&Bridge> {
+        let name = source.as_ref()?;
+        self.bridges.iter().find(|bridge| &bridge.name == name)
+    }
+
+    /// Every configured source, the bridge serving the page first.
+    pub fn sources(&self) -> impl Iterator<Item = Source> + '_ {
+        std::iter::once(None).chain(self.bridges.iter().map(|bridge| Some(bridge.name.clone())))
+    }
+
+    /// Whether messages are tagged with their source in the chat.
+    pub fn several(&self) -> bool {
+        !self.bridges.is_empty()
+    }
+
+    pub fn name<'a>(&'a self, source: &'a Source) -> &'a str {
+        source.as_deref().unwrap_or(&self.primary_name)
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{models::NewMessageRequest, storage};
+
+const STORAGE_KEY: &str = "split";
+const HISTORY_KEY: &str = "inputHistory";
+const DRAFTS_KEY: &str = "drafts";
+/// Number of sent messages that can be recalled.
+const MAX_HISTORY: usize = 100;
+
+/// How messages over the game's length limit are handled. Unless enabled they are refused.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplitSettings {
+    pub enabled: bool,
+    /// Whether the parts end with their position, e.g. `(1/3)`.
+    pub numbered: bool,
+}
+
+pub enum SplitUpdate {
+    Enabled(bool),
+    Numbered(bool),
+}
+
+impl Default for SplitSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            numbered: true,
+        }
+    }
+}
+
+impl SplitSettings {
+    pub fn load() -> Self {
+        storage::load::<Self>(STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn apply(&mut self, update: SplitUpdate) {
+        match update {
+            SplitUpdate::Enabled(enabled) => self.enabled = enabled,
+            SplitUpdate::Numbered(numbered) => self.numbered = numbered,
+        }
+        storage::save(STORAGE_KEY, self);
+    }
+}
+
+/// Sent messages, recalled with Up and Down like in the game's chat input. Oldest first.
+#[derive(Default, Serialize, Deserialize)]
+pub struct InputHistory {
+    entries: Vec<NewMessageRequest>,
+    /// The recalled entry, `None` while editing a new message.
+    #[serde(skip)]
+    position: Option<usize>,
+}
+
+pub enum Recall<'a> {
+    Entry(&'a NewMessageRequest),
+    /// Went past the newest entry, back to what was typed before recalling.
+    Draft,
+}
+
+/// Ctrl+R search through the sent messages.
+#[derive(Default)]
+pub struct HistorySearch {
+    pub query: String,
+    /// Index of the matching entry.
+    pub found: Option<usize>,
+}
+
+impl InputHistory {
+    pub fn load() -> Self {
+        storage::load::<Self>(HISTORY_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(HISTORY_KEY, self);
+    }
+
+    /// Remembers a sent message, unless it repeats the previous one.
+    pub fn push(&mut self, request: NewMessageRequest) {
+        self.position = None;
+        if self.entries.last() == Some(&request) {
+            return;
+        }
+        self.entries.push(request);
+        let over_limit = self.entries.len().saturating_sub(MAX_HISTORY);
+        self.entries.drain(..over_limit);
+    }
+
+    pub fn get(&self, index: usize) -> Option<&NewMessageRequest> {
+        self.entries.get(index)
+    }
+
+    /// Whether an entry is recalled.
+    pub fn recalling(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// Stops recalling, e.g. once the recalled message was edited.
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+
+    /// Recalls the entry before the current one. `None` if there is none.
+    pub fn older(&mut self) -> Option<&NewMessageRequest> {
+        let position = match self.position {
+            None => self.entries.len().checked_sub(1)?,
+            Some(position) => position.checked_sub(1)?,
+        };
+        self.position = Some(position);
+        self.entries.get(position)
+    }
+
+    /// Recalls the entry after the current one. `None` if nothing is recalled.
+    pub fn newer(&mut self) -> Option<Recall<'_>> {
+        let position = self.position? + 1;
+        if position >= self.entries.len() {
+            self.position = None;
+            return Some(Recall::Draft);
+        }
+        self.position = Some(position);
+        self.entries.get(position).map(Recall::Entry)
+    }
+
+    /// The index of the newest entry before `before` containing `query`, ignoring case.
+    pub fn search(&self, query: &str, before: Option<usize>) -> Option<usize> {
+        let query = query.to_lowercase();
+        let end = before.unwrap_or(self.entries.len()).min(self.entries.len());
+        self.entries[..end]
+            .iter()
+            .rposition(|entry| entry.text.to_lowercase().contains(&query))
+    }
+}
+
+/// Unsent text of the input form per channel, kept across reloads.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Drafts(BTreeMap<String, String>);
+
+impl Drafts {
+    pub fn load() -> Self {
+        storage::load::<Self>(DRAFTS_KEY).unwrap_or_default()
+    }
+
+    pub fn get(&self, channel: &str) -> String {
+        self.0.get(channel).cloned().unwrap_or_default()
+    }
+
+    /// Remembers `text` as the draft of `channel`, forgetting it once empty.
+    pub fn set(&mut self, channel: &str, text: &str) {
+        let changed = if text.is_empty() {
+            self.0.remove(channel).is_some()
+        } else {
+            self.0
+                .insert(channel.to_owned(), text.to_owned())
+                .as_deref()
+                != Some(text)
+        };
+        if !changed {
+            return;
+        }
+        if self.0.is_empty() {
+            storage::remove(DRAFTS_KEY);
+        } else {
+            storage::save(DRAFTS_KEY, self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{super::models::ChatType, InputHistory, NewMessageRequest, Recall};
+
+    #[test]
+    fn test_input_history() {
+        let say = |text: &str| NewMessageRequest {
+            chat_type: ChatType::Say,
+            text: text.to_owned(),
+            target: None,
+        };
+        let mut history = InputHistory::default();
+        assert!(history.older().is_none());
+        assert!(history.newer().is_none());
+        history.push(say("hello"));
+        history.push(say("Hello there"));
+        history.push(say("Hello there"));
+        history.push(say("bye"));
+
+        assert_eq!(history.older().unwrap().text, "bye");
+        assert!(history.recalling());
+        assert_eq!(history.older().unwrap().text, "Hello there");
+        assert_eq!(history.older().unwrap().text, "hello");
+        assert!(history.older().is_none());
+        assert!(
+            matches!(history.newer(), Some(Recall::Entry(entry)) if entry.text == "Hello there")
+        );
+        assert!(matches!(history.newer(), Some(Recall::Entry(entry)) if entry.text == "bye"));
+        assert!(matches!(history.newer(), Some(Recall::Draft)));
+        assert!(!history.recalling());
+        assert!(history.newer().is_none());
+
+        assert_eq!(history.search("HELLO", None), Some(1));
+        assert_eq!(history.search("hello", Some(1)), Some(0));
+        assert_eq!(history.search("hello", Some(0)), None);
+        assert_eq!(history.search("", None), Some(2));
+
+        // sending stops recalling
+        history.older();
+        history.push(say("again"));
+        assert!(!history.recalling());
+        assert_eq!(history.older().unwrap().text, "again");
+    }
+}
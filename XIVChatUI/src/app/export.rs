@@ -0,0 +1,260 @@
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+use super::models::{ChatMessage, CROSS_WORLD_ICON};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    /// Lines as shown in the game's chat log.
+    Text,
+    /// One message per line in the plugin's JSON format.
+    JsonLines,
+    Csv,
+    /// Standalone page with the chat colors.
+    Html,
+}
+
+pub enum ExportUpdate {
+    Format(ExportFormat),
+    /// Whether only the messages shown in the current tab or search are exported.
+    Filtered(bool),
+}
+
+pub struct ExportSettings {
+    pub format: ExportFormat,
+    pub filtered: bool,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Text,
+            filtered: true,
+        }
+    }
+}
+
+impl ExportSettings {
+    pub fn apply(&mut self, update: ExportUpdate) {
+        match update {
+            ExportUpdate::Format(format) => self.format = format,
+            ExportUpdate::Filtered(filtered) => self.filtered = filtered,
+        }
+    }
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [Self::Text, Self::JsonLines, Self::Csv, Self::Html];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Text => "Text",
+            Self::JsonLines => "JSON lines",
+            Self::Csv => "CSV",
+            Self::Html => "HTML",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::JsonLines => "jsonl",
+            Self::Csv => "csv",
+            Self::Html => "html",
+        }
+    }
+
+    fn mime_type(self) -> &'static str {
+        match self {
+            Self::Text => "text/plain",
+            Self::JsonLines => "application/jsonl",
+            Self::Csv => "text/csv",
+            Self::Html => "text/html",
+        }
+    }
+
+    /// Serializes `messages`, oldest first.
+    pub fn export<'a>(self, messages: impl Iterator<Item = &'a ChatMessage>) -> String {
+        let mut out = String::new();
+        if self == Self::Csv {
+            out.push_str("timestamp,channel,sender,text\r\n");
+        } else if self == Self::Html {
+            out.push_str(HTML_HEAD);
+        }
+        for msg in messages {
+            match self {
+                Self::Text => {
+                    out.push_str(&format!(
+                        "[{}] {}\n",
+                        msg.formatted_timestamp(),
+                        chat_line(msg)
+                    ));
+                }
+                Self::JsonLines => {
+                    out.push_str(&serde_json::to_string(msg).unwrap_or_default());
+                    out.push('\n');
+                }
+                Self::Csv => {
+                    let fields = [
+                        msg.timestamp.as_str(),
+                        &channel_name(msg),
+                        &sender(msg),
+                        &msg.text,
+                    ];
+                    let fields: Vec<String> = fields.into_iter().map(csv_field).collect();
+                    out.push_str(&fields.join(","));
+                    out.push_str("\r\n");
+                }
+                Self::Html => {
+                    out.push_str(&format!(
+                        "<div><span class=\"timestamp\">[{}]</span> <span style=\"color: {}\">{}</span></div>\n",
+                        msg.formatted_timestamp(),
+                        msg.chat_type.get_color(),
+                        escape_html(&chat_line(msg)),
+                    ));
+                }
+            }
+        }
+        if self == Self::Html {
+            out.push_str("</body>\n</html>\n");
+        }
+        out
+    }
+
+    /// Exports `messages` and hands the result to the browser as a file download.
+    pub fn download<'a>(
+        self,
+        messages: impl Iterator<Item = &'a ChatMessage>,
+    ) -> Result<(), JsValue> {
+        let content = JsValue::from_str(&self.export(messages));
+        let mut options = BlobPropertyBag::new();
+        options.type_(&format!("{};charset=utf-8", self.mime_type()));
+        let blob =
+            Blob::new_with_str_sequence_and_options(&js_sys::Array::of1(&content), &options)?;
+        let url = Url::create_object_url_with_blob(&blob)?;
+
+        let document = web_sys::window()
+            .and_then(|w| w.document())
+            .ok_or_else(|| JsValue::from_str("no document"))?;
+        let link: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+        link.set_href(&url);
+        link.set_download(&format!(
+            "xivchat-{}.{}",
+            chrono::Local::now().format("%Y-%m-%d-%H%M"),
+            self.extension()
+        ));
+        link.click();
+        Url::revoke_object_url(&url)
+    }
+}
+
+const HTML_HEAD: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>XIVChat</title>
+<style>
+body { background-color: #1d1d1d; color: #e6e6e6; font-family: sans-serif; }
+.timestamp { color: #a0a0a0; }
+</style>
+</head>
+<body>
+";
+
+fn sender(msg: &ChatMessage) -> String {
+    msg.sender_name.replace(CROSS_WORLD_ICON, "@")
+}
+
+/// The `XivChatType` name, labels are ambiguous for machine readable formats.
+fn channel_name(msg: &ChatMessage) -> String {
+    match serde_json::to_value(&msg.chat_type) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => msg.chat_type.to_string(),
+    }
+}
+
+/// A message as shown in the game, without the timestamp.
+fn chat_line(msg: &ChatMessage) -> String {
+    let mut line = String::new();
+    let label = msg.chat_type.to_string();
+    if !label.is_empty() {
+        line.push_str(&format!("[{label}] "));
+    }
+    if !msg.sender_name.is_empty() {
+        line.push_str(&format!("{}: ", sender(msg)));
+    }
+    line.push_str(&msg.text);
+    line
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        super::{
+            models::ChatType,
+            test_support::{chat_message, TIMESTAMP},
+        },
+        ChatMessage, ExportFormat,
+    };
+
+    #[test]
+    fn test_export() {
+        let messages = [
+            chat_message(
+                TIMESTAMP,
+                ChatType::FreeCompany,
+                "Alpha Beta\u{e05d}Phoenix",
+                "see you at <8>, \"sharp\"",
+            ),
+            chat_message(
+                "2024-07-14T10:19:00.0000000+02:00",
+                ChatType::Echo,
+                "",
+                "done",
+            ),
+        ];
+
+        assert_eq!(
+            ExportFormat::Text.export(messages.iter()),
+            format!(
+                "[{}] [FC] Alpha Beta@Phoenix: see you at <8>, \"sharp\"\n[{}] [Echo] done\n",
+                messages[0].formatted_timestamp(),
+                messages[1].formatted_timestamp(),
+            )
+        );
+        assert_eq!(
+            ExportFormat::Csv.export(messages.iter()),
+            "timestamp,channel,sender,text\r\n\
+             2024-07-14T10:18:02.8379913+02:00,freeCompany,Alpha Beta@Phoenix,\"see you at <8>, \"\"sharp\"\"\"\r\n\
+             2024-07-14T10:19:00.0000000+02:00,echo,,done\r\n"
+        );
+
+        let json = ExportFormat::JsonLines.export(messages.iter());
+        let parsed: Vec<ChatMessage> = json
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(parsed, messages);
+
+        let html = ExportFormat::Html.export(messages.iter());
+        assert!(html.contains(
+            "<span style=\"color: #4ef542\">[FC] Alpha Beta@Phoenix: see you at &lt;8&gt;, &quot;sharp&quot;</span>"
+        ));
+        assert!(html.ends_with("</html>\n"));
+    }
+}
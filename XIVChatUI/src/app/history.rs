@@ -0,0 +1,257 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    EventTarget, IdbDatabase, IdbKeyRange, IdbObjectStore, IdbObjectStoreParameters,
+    IdbOpenDbRequest, IdbRequest, IdbTransactionMode,
+};
+
+use super::{models::ChatMessage, storage};
+
+const STORAGE_KEY: &str = "history";
+const DB_NAME: &str = "xivchat";
+const DB_VERSION: u32 = 1;
+const STORE_NAME: &str = "messages";
+
+/// How much of the chat is kept on this device.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    pub enabled: bool,
+    /// Messages older than this are removed, 0 keeps them regardless of age.
+    pub max_days: u32,
+    pub max_messages: usize,
+}
+
+pub enum HistoryUpdate {
+    Enabled(bool),
+    MaxDays(u32),
+    MaxMessages(usize),
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_days: 30,
+            max_messages: 10000,
+        }
+    }
+}
+
+impl HistorySettings {
+    pub fn load() -> Self {
+        storage::load::<Self>(STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn apply(&mut self, update: HistoryUpdate) {
+        match update {
+            HistoryUpdate::Enabled(enabled) => self.enabled = enabled,
+            HistoryUpdate::MaxDays(max_days) => self.max_days = max_days,
+            HistoryUpdate::MaxMessages(max_messages) => self.max_messages = max_messages,
+        }
+        storage::save(STORAGE_KEY, self);
+    }
+
+    /// The number of messages at the start of `messages`, which is ordered by timestamp, that
+    /// are past the retention limits.
+    pub fn expired(&self, messages: &[ChatMessage], now: chrono::DateTime<chrono::Utc>) -> usize {
+        let over_limit = messages.len().saturating_sub(self.max_messages);
+        let Some(cutoff) = self.cutoff(now) else {
+            return over_limit;
+        };
+        let too_old = messages
+            .iter()
+            .take_while(|msg| msg.parsed_timestamp().is_none_or(|ts| ts < cutoff))
+            .count();
+        over_limit.max(too_old)
+    }
+
+    /// Whether a received `msg` is within the retention limits. `kept_since` is the timestamp
+    /// of the oldest message left once older ones were removed, bridges still send those.
+    pub fn retains(
+        &self,
+        msg: &ChatMessage,
+        now: chrono::DateTime<chrono::Utc>,
+        kept_since: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> bool {
+        match self.cutoff(now).max(kept_since) {
+            Some(cutoff) => msg.parsed_timestamp().is_some_and(|ts| ts >= cutoff),
+            None => true,
+        }
+    }
+
+    /// Messages older than this are past the maximum age, if there is one.
+    fn cutoff(&self, now: chrono::DateTime<chrono::Utc>) -> Option<chrono::DateTime<chrono::Utc>> {
+        (self.max_days != 0).then(|| now - chrono::Duration::days(i64::from(self.max_days)))
+    }
+}
+
+/// Messages received from the bridge, stored in IndexedDB so they outlive the plugin's
+/// message limit. Records are keyed by `[time, id]`, which keeps them in
+/// chronological order and allows removing expired ones with a single key range.
+#[derive(Clone)]
+pub struct History {
+    db: IdbDatabase,
+}
+
+impl History {
+    /// Opens the database and reads all stored messages, oldest first.
+    pub async fn open() -> Result<(Self, Vec<ChatMessage>), JsValue> {
+        let factory = web_sys::window()
+            .ok_or_else(|| JsValue::from_str("no window"))?
+            .indexed_db()?
+            .ok_or_else(|| JsValue::from_str("IndexedDB is not supported"))?;
+        let request = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+        let upgrade = request.clone();
+        let on_upgrade = wasm_bindgen::closure::Closure::once_into_js(move || {
+            if let Err(e) = create_store(&upgrade) {
+                log::error!("Unable to create message history: {e:?}");
+            }
+        });
+        request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+        let history = Self {
+            db: request_result(&request).await?.unchecked_into(),
+        };
+
+        let records = request_result(&history.store(IdbTransactionMode::Readonly)?.get_all()?)
+            .await?
+            .unchecked_into::<js_sys::Array>();
+        let messages = records
+            .iter()
+            .filter_map(|record| {
+                js_sys::Reflect::get(&record, &"message".into())
+                    .ok()?
+                    .as_string()
+            })
+            .filter_map(|json| serde_json::from_str::<ChatMessage>(&json).ok())
+            .collect();
+        Ok((history, messages))
+    }
+
+    /// Adds `messages`, replacing the ones already stored.
+    pub async fn save(&self, messages: &[ChatMessage]) -> Result<(), JsValue> {
+        let store = self.store(IdbTransactionMode::Readwrite)?;
+        for msg in messages {
+            let record = js_sys::Object::new();
+            js_sys::Reflect::set(&record, &"key".into(), &key(msg))?;
+            js_sys::Reflect::set(
+                &record,
+                &"message".into(),
+                &serde_json::to_string(msg)
+                    .map_err(|e| JsValue::from_str(&e.to_string()))?
+                    .into(),
+            )?;
+            store.put(&record)?;
+        }
+        transaction_complete(&store).await
+    }
+
+    /// Removes the messages older than `msg`.
+    pub async fn remove_before(&self, msg: &ChatMessage) -> Result<(), JsValue> {
+        let store = self.store(IdbTransactionMode::Readwrite)?;
+        let bound = js_sys::Array::of1(&time(msg).into());
+        let range = IdbKeyRange::upper_bound_with_open(&bound, true)?;
+        store.delete(&range)?;
+        transaction_complete(&store).await
+    }
+
+    pub async fn clear(&self) -> Result<(), JsValue> {
+        let store = self.store(IdbTransactionMode::Readwrite)?;
+        store.clear()?;
+        transaction_complete(&store).await
+    }
+
+    fn store(&self, mode: IdbTransactionMode) -> Result<IdbObjectStore, JsValue> {
+        self.db
+            .transaction_with_str_and_mode(STORE_NAME, mode)?
+            .object_store(STORE_NAME)
+    }
+}
+
+fn create_store(request: &IdbOpenDbRequest) -> Result<(), JsValue> {
+    let db: IdbDatabase = request.result()?.unchecked_into();
+    let mut parameters = IdbObjectStoreParameters::new();
+    parameters.key_path(Some(&"key".into()));
+    db.create_object_store_with_optional_parameters(STORE_NAME, &parameters)?;
+    Ok(())
+}
+
+/// Milliseconds since the epoch, messages with invalid timestamps sort first.
+fn time(msg: &ChatMessage) -> f64 {
+    msg.parsed_timestamp()
+        .map_or(0.0, |ts| ts.timestamp_millis() as f64)
+}
+
+fn key(msg: &ChatMessage) -> JsValue {
+    js_sys::Array::of2(&time(msg).into(), &msg.id().to_string().into()).into()
+}
+
+/// Waits for `success_event` on `target`, failing on its `error` event.
+async fn wait_for(target: &EventTarget, success_event: &str) -> Result<(), JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let _ = target.add_event_listener_with_callback(success_event, &resolve);
+        let _ = target.add_event_listener_with_callback("error", &reject);
+        let _ = target.add_event_listener_with_callback("abort", &reject);
+    });
+    JsFuture::from(promise).await.map(drop)
+}
+
+async fn request_result(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    if wait_for(request, "success").await.is_err() {
+        return Err(request
+            .error()
+            .ok()
+            .flatten()
+            .map_or_else(|| JsValue::from_str("request failed"), JsValue::from));
+    }
+    request.result()
+}
+
+async fn transaction_complete(store: &IdbObjectStore) -> Result<(), JsValue> {
+    let transaction = store.transaction();
+    wait_for(&transaction, "complete").await.map_err(|_| {
+        transaction
+            .error()
+            .map_or_else(|| JsValue::from_str("transaction failed"), JsValue::from)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{super::test_support::message, HistorySettings};
+
+    #[test]
+    fn test_history_retention() {
+        let messages = [
+            message("2024-06-01T10:00:00.0000000+02:00", "test"),
+            message("2024-07-10T10:00:00.0000000+02:00", "test"),
+            message("2024-07-13T10:00:00.0000000+02:00", "test"),
+            message("2024-07-14T10:00:00.0000000+02:00", "test"),
+        ];
+        let now = "2024-07-14T12:00:00Z".parse().unwrap();
+
+        let mut settings = HistorySettings {
+            enabled: true,
+            max_days: 7,
+            max_messages: 10,
+        };
+        assert_eq!(settings.expired(&messages, now), 1);
+        settings.max_messages = 2;
+        assert_eq!(settings.expired(&messages, now), 2);
+        settings.max_days = 1;
+        assert_eq!(settings.expired(&messages, now), 3);
+        settings.max_days = 0;
+        settings.max_messages = 10;
+        assert_eq!(settings.expired(&messages, now), 0);
+
+        // received again after they were removed
+        assert!(settings.retains(&messages[0], now, None));
+        let kept_since = messages[2].parsed_timestamp();
+        assert!(!settings.retains(&messages[1], now, kept_since));
+        assert!(settings.retains(&messages[2], now, kept_since));
+        settings.max_days = 7;
+        assert!(!settings.retains(&messages[0], now, None));
+        assert!(settings.retains(&messages[1], now, None));
+    }
+}
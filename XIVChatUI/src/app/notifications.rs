@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{closure::Closure, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Notification, NotificationOptions, NotificationPermission};
+use yew::Callback;
+
+use super::{
+    models::{ChatMessage, ChatType, CROSS_WORLD_ICON},
+    storage,
+};
+
+const STORAGE_KEY: &str = "notifications";
+/// Upper limit of notifications shown for one update, e.g. when catching up after a reconnect.
+const MAX_PER_UPDATE: usize = 3;
+
+/// Which messages raise a desktop notification while the page is in the background.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// Channels that notify on every message.
+    pub channels: Vec<ChatType>,
+    /// Words that notify on any channel, matched ignoring case.
+    pub keywords: Vec<String>,
+}
+
+pub enum NotificationUpdate {
+    Enabled(bool),
+    ToggleChannel(ChatType),
+    /// Comma separated list of keywords.
+    Keywords(String),
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channels: vec![ChatType::TellIncoming],
+            keywords: Vec::new(),
+        }
+    }
+}
+
+impl NotificationSettings {
+    pub fn load() -> Self {
+        storage::load::<Self>(STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn apply(&mut self, update: NotificationUpdate) {
+        match update {
+            NotificationUpdate::Enabled(enabled) => self.enabled = enabled,
+            NotificationUpdate::ToggleChannel(chat_type) => {
+                if let Some(index) = self.channels.iter().position(|c| *c == chat_type) {
+                    self.channels.remove(index);
+                } else {
+                    self.channels.push(chat_type);
+                }
+            }
+            NotificationUpdate::Keywords(keywords) => {
+                self.keywords = keywords
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+            }
+        }
+        storage::save(STORAGE_KEY, self);
+    }
+
+    pub fn should_notify(&self, msg: &ChatMessage) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if self.channels.contains(&msg.chat_type) {
+            return true;
+        }
+        let text = msg.text.to_lowercase();
+        self.keywords
+            .iter()
+            .any(|keyword| text.contains(&keyword.to_lowercase()))
+    }
+
+    /// Notifies about the matching messages of `new`, if the page is not visible. `on_click`
+    /// gets the key of the clicked message.
+    pub fn notify<'a>(
+        &self,
+        new: impl DoubleEndedIterator<Item = &'a ChatMessage>,
+        key: impl Fn(&ChatMessage) -> String,
+        on_click: &Callback<String>,
+    ) {
+        if !self.enabled || permission() != Some(NotificationPermission::Granted) || !page_hidden()
+        {
+            return;
+        }
+        for msg in new
+            .rev()
+            .filter(|msg| self.should_notify(msg))
+            .take(MAX_PER_UPDATE)
+        {
+            show(msg, key(msg), on_click.clone());
+        }
+    }
+}
+
+/// The permission for notifications, `None` if the browser does not support them.
+pub fn permission() -> Option<NotificationPermission> {
+    web_sys::window()
+        .filter(|w| w.get("Notification").is_some())
+        .map(|_| Notification::permission())
+}
+
+/// Asks the user for permission to show notifications, returns whether it was granted.
+pub async fn request_permission() -> bool {
+    let Ok(promise) = Notification::request_permission() else {
+        return false;
+    };
+    JsFuture::from(promise)
+        .await
+        .ok()
+        .and_then(|permission| permission.as_string())
+        .is_some_and(|permission| permission == "granted")
+}
+
+fn page_hidden() -> bool {
+    web_sys::window()
+        .and_then(|w| w.document())
+        .is_some_and(|doc| doc.hidden())
+}
+
+fn show(msg: &ChatMessage, key: String, on_click: Callback<String>) {
+    let title = if msg.sender_name.is_empty() {
+        format!("[{}]", msg.chat_type)
+    } else {
+        format!(
+            "[{}] {}",
+            msg.chat_type,
+            msg.sender_name.replace(CROSS_WORLD_ICON, "@")
+        )
+    };
+    let mut options = NotificationOptions::new();
+    options.body(&msg.text).tag(&key);
+    let notification = match Notification::new_with_options(&title, &options) {
+        Ok(notification) => notification,
+        Err(e) => {
+            log::error!("Unable to show notification: {e:?}");
+            return;
+        }
+    };
+
+    let clicked = notification.clone();
+    let onclick = Closure::once_into_js(move || {
+        if let Some(window) = web_sys::window() {
+            let _ = window.focus();
+        }
+        clicked.close();
+        on_click.emit(key);
+    });
+    notification.set_onclick(Some(onclick.unchecked_ref()));
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        super::test_support::{message, TIMESTAMP},
+        ChatType, NotificationSettings,
+    };
+
+    #[test]
+    fn test_notifications() {
+        let mut settings = NotificationSettings {
+            keywords: vec!["Savage".to_owned()],
+            ..Default::default()
+        };
+        let mut msg = message(TIMESTAMP, "anyone up for savage?");
+        assert!(!settings.should_notify(&msg));
+
+        settings.enabled = true;
+        assert!(settings.should_notify(&msg));
+
+        msg.text = "hi".to_owned();
+        assert!(!settings.should_notify(&msg));
+        msg.chat_type = ChatType::TellIncoming;
+        assert!(settings.should_notify(&msg));
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bridges::Source,
+    models::{ChatMessage, NewMessageRequest, TellTarget},
+    storage, ErrorKind, ErrorMessage,
+};
+
+const STORAGE_KEY: &str = "outbox";
+/// How far the clock of the game may be behind the one of the browser.
+const CLOCK_SKEW_SEC: i64 = 60;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub enum QueueState {
+    /// Waiting to be sent, retried whenever the bridge is reachable.
+    Pending,
+    Sending,
+    /// Accepted by the bridge, waiting for the game to echo it into the chat.
+    Sent,
+    /// Rejected or never echoed, only sent again on request.
+    Failed,
+}
+
+/// A message that was submitted but not yet accepted by the bridge.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct QueuedMessage {
+    pub id: u64,
+    pub request: NewMessageRequest,
+    /// The bridge the message is sent through.
+    #[serde(default)]
+    pub bridge: Source,
+    /// When the message was submitted, in the format of `ChatMessage::timestamp`.
+    pub submitted: String,
+    pub state: QueueState,
+    pub attempts: u32,
+    /// Description of the last error.
+    pub error: Option<String>,
+}
+
+pub enum OutboxAction {
+    Resend(u64),
+    /// Moves the message back into the input form.
+    Edit(u64),
+    Discard(u64),
+}
+
+/// Unsent messages, kept in local storage so nothing is lost when the bridge is down or the
+/// page is reloaded. Messages are sent one at a time per bridge, in the order they were
+/// submitted.
+#[derive(Default, Deserialize, Serialize)]
+pub struct Outbox {
+    pub messages: Vec<QueuedMessage>,
+    next_id: u64,
+    /// Name the player is shown with per bridge, learned from the echoes of their messages.
+    #[serde(skip)]
+    senders: HashMap<Source, String>,
+}
+
+impl Outbox {
+    pub fn load() -> Self {
+        let mut outbox = storage::load::<Self>(STORAGE_KEY).unwrap_or_default();
+        // the page was closed before the bridge answered
+        for msg in &mut outbox.messages {
+            if msg.state == QueueState::Sending {
+                msg.state = QueueState::Pending;
+            }
+        }
+        outbox
+    }
+
+    pub fn save(&self) {
+        if self.messages.is_empty() {
+            storage::remove(STORAGE_KEY);
+        } else {
+            storage::save(STORAGE_KEY, self);
+        }
+    }
+
+    pub fn push(&mut self, request: NewMessageRequest, bridge: Source) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.messages.push(QueuedMessage {
+            id,
+            request,
+            bridge,
+            submitted: chrono::Utc::now().to_rfc3339(),
+            state: QueueState::Pending,
+            attempts: 0,
+            error: None,
+        });
+        id
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<QueuedMessage> {
+        let index = self.messages.iter().position(|msg| msg.id == id)?;
+        Some(self.messages.remove(index))
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut QueuedMessage> {
+        self.messages.iter_mut().find(|msg| msg.id == id)
+    }
+
+    /// The next message to send through `bridge`, unless one is already on its way.
+    pub fn next_to_send(&mut self, bridge: &Source) -> Option<&mut QueuedMessage> {
+        if self
+            .messages
+            .iter()
+            .any(|msg| &msg.bridge == bridge && msg.state == QueueState::Sending)
+        {
+            return None;
+        }
+        self.messages
+            .iter_mut()
+            .find(|msg| &msg.bridge == bridge && msg.state == QueueState::Pending)
+    }
+
+    /// Records a failed attempt. Returns whether the message is retried automatically.
+    pub fn failed(&mut self, id: u64, error: &ErrorMessage) -> bool {
+        let Some(msg) = self.get_mut(id) else {
+            return false;
+        };
+        msg.attempts += 1;
+        msg.error = Some(error.description.clone());
+        // anything but a rejected message is worth another try once the bridge is back
+        msg.state = match error.kind {
+            ErrorKind::Rejected | ErrorKind::Input => QueueState::Failed,
+            ErrorKind::Connection
+            | ErrorKind::Unauthorized
+            | ErrorKind::Http
+            | ErrorKind::Parse => QueueState::Pending,
+        };
+        msg.state == QueueState::Pending
+    }
+
+    /// Marks a message as accepted by the bridge.
+    pub fn sent(&mut self, id: u64) {
+        if let Some(msg) = self.get_mut(id) {
+            msg.state = QueueState::Sent;
+            msg.error = None;
+        }
+    }
+
+    /// Removes the sent message `msg` is the echo of. Returns whether there was one.
+    pub fn confirm(&mut self, msg: &ChatMessage) -> bool {
+        let sender = self.senders.get(&msg.source).map(String::as_str);
+        let echoed = self
+            .messages
+            .iter()
+            .find(|queued| queued.state == QueueState::Sent && is_echo(queued, msg, sender))
+            .map(|queued| queued.id);
+        let Some(queued) = echoed.and_then(|id| self.remove(id)) else {
+            return false;
+        };
+        // tells are echoed with the recipient as sender
+        if queued.request.target.is_none() {
+            self.senders
+                .insert(msg.source.clone(), msg.sender_name.clone());
+        }
+        true
+    }
+
+    /// Gives up on a sent message that did not show up in the chat. Returns whether it was
+    /// still waiting for it.
+    pub fn unconfirmed(&mut self, id: u64) -> bool {
+        match self.get_mut(id) {
+            Some(msg) if msg.state == QueueState::Sent => {
+                msg.state = QueueState::Failed;
+                msg.error = Some("The message did not show up in the chat".to_owned());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn has_pending(&self, bridge: &Source) -> bool {
+        self.messages
+            .iter()
+            .any(|msg| &msg.bridge == bridge && msg.state == QueueState::Pending)
+    }
+}
+
+impl QueuedMessage {
+    pub fn formatted_submitted(&self) -> String {
+        self.submitted
+            .parse::<chrono::DateTime<chrono::Utc>>()
+            .map(|date_time| {
+                chrono::DateTime::<chrono::Local>::from(date_time)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_else(|_| "N/A".to_owned())
+    }
+}
+
+/// Whether `msg` is how the game shows `queued` in the chat, `sender` is the name of the
+/// player if known. The game echoes tells as `TellOutgoing` with the recipient as sender.
+fn is_echo(queued: &QueuedMessage, msg: &ChatMessage, sender: Option<&str>) -> bool {
+    let request = &queued.request;
+    // not the same text said before the message was submitted
+    let earliest = queued
+        .submitted
+        .parse::<chrono::DateTime<chrono::Utc>>()
+        .ok()
+        .map(|submitted| submitted - chrono::Duration::seconds(CLOCK_SKEW_SEC));
+    let said_after = msg
+        .parsed_timestamp()
+        .zip(earliest)
+        .is_some_and(|(timestamp, earliest)| timestamp >= earliest);
+    if msg.source != queued.bridge
+        || msg.chat_type != request.chat_type
+        || msg.text.trim() != request.text.trim()
+        || !said_after
+    {
+        return false;
+    }
+    match &request.target {
+        Some(target) => TellTarget::from_sender(&msg.sender_name)
+            .is_some_and(|recipient| recipient.name == target.name),
+        None => sender.is_none_or(|sender| msg.sender_name == sender),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        super::{
+            models::ChatType,
+            test_support::{chat_message, TIMESTAMP},
+        },
+        ErrorKind, ErrorMessage, NewMessageRequest, Outbox, QueueState, TellTarget,
+    };
+
+    #[test]
+    fn test_outbox() {
+        let request = |text: &str| NewMessageRequest {
+            chat_type: ChatType::Say,
+            text: text.to_owned(),
+            target: None,
+        };
+        let error = |kind| ErrorMessage {
+            kind,
+            description: "failed".to_owned(),
+            details: None,
+        };
+
+        let mut outbox = Outbox::default();
+        let first = outbox.push(request("first"), None);
+        let second = outbox.push(request("second"), None);
+        let other = Some("Alt".to_owned());
+        let elsewhere = outbox.push(request("elsewhere"), other.clone());
+
+        let next = outbox.next_to_send(&None).unwrap();
+        assert_eq!(next.id, first);
+        next.state = QueueState::Sending;
+        assert!(outbox.next_to_send(&None).is_none());
+        // bridges do not wait for each other
+        assert_eq!(
+            outbox.next_to_send(&other).map(|msg| msg.id),
+            Some(elsewhere)
+        );
+
+        assert!(outbox.failed(first, &error(ErrorKind::Connection)));
+        assert_eq!(outbox.next_to_send(&None).map(|msg| msg.id), Some(first));
+
+        assert!(!outbox.failed(first, &error(ErrorKind::Rejected)));
+        assert_eq!(outbox.get_mut(first).unwrap().state, QueueState::Failed);
+        assert_eq!(outbox.get_mut(first).unwrap().attempts, 2);
+        assert_eq!(outbox.next_to_send(&None).map(|msg| msg.id), Some(second));
+
+        outbox.remove(second);
+        assert!(!outbox.has_pending(&None));
+        assert!(outbox.has_pending(&other));
+    }
+
+    #[test]
+    fn test_local_echo() {
+        let echo =
+            |chat_type, sender: &str, text: &str| chat_message(TIMESTAMP, chat_type, sender, text);
+
+        let mut outbox = Outbox::default();
+        let tell = outbox.push(
+            NewMessageRequest {
+                chat_type: ChatType::TellOutgoing,
+                text: "hi ".to_owned(),
+                target: Some("Alpha Beta@Phoenix".parse::<TellTarget>().unwrap()),
+            },
+            None,
+        );
+        let say = outbox.push(
+            NewMessageRequest {
+                chat_type: ChatType::Say,
+                text: "hello".to_owned(),
+                target: None,
+            },
+            None,
+        );
+        let again = outbox.push(
+            NewMessageRequest {
+                chat_type: ChatType::Say,
+                text: "hello".to_owned(),
+                target: None,
+            },
+            None,
+        );
+        for msg in &mut outbox.messages {
+            msg.submitted = TIMESTAMP.to_owned();
+        }
+
+        // only messages accepted by the bridge are confirmed
+        let tell_echo = echo(ChatType::TellOutgoing, "Alpha Beta\u{e05d}Phoenix", "hi");
+        assert!(!outbox.confirm(&tell_echo));
+        outbox.sent(tell);
+        outbox.sent(say);
+        assert!(!outbox.confirm(&echo(ChatType::TellOutgoing, "Gamma Delta", "hi")));
+        assert!(!outbox.confirm(&echo(ChatType::Party, "Alpha Beta", "hello")));
+        // said before it was submitted, a minute of clock skew aside
+        let earlier = chat_message(
+            "2024-07-14T10:16:02+02:00",
+            ChatType::Say,
+            "Epsilon Zeta",
+            "hello",
+        );
+        assert!(!outbox.confirm(&earlier));
+        let skewed = chat_message(
+            "2024-07-14T10:17:30+02:00",
+            ChatType::Say,
+            "Epsilon Zeta",
+            "hello",
+        );
+        assert!(outbox.confirm(&skewed));
+        assert!(outbox.get_mut(say).is_none());
+        // only the player's own messages once their name is known
+        outbox.sent(again);
+        assert!(!outbox.confirm(&echo(ChatType::Say, "Eta Theta", "hello")));
+        assert!(outbox.confirm(&echo(ChatType::Say, "Epsilon Zeta", "hello")));
+        // the same text through another bridge
+        let mut other = tell_echo.clone();
+        other.source = Some("Alt".to_owned());
+        assert!(!outbox.confirm(&other));
+        assert!(outbox.confirm(&tell_echo));
+        assert!(outbox.get_mut(tell).is_none());
+
+        let last = outbox.push(
+            NewMessageRequest {
+                chat_type: ChatType::Say,
+                text: "bye".to_owned(),
+                target: None,
+            },
+            None,
+        );
+        outbox.sent(last);
+        assert!(outbox.unconfirmed(last));
+        assert!(!outbox.unconfirmed(last));
+        assert_eq!(outbox.get_mut(last).unwrap().state, QueueState::Failed);
+    }
+}
@@ -0,0 +1,109 @@
+use yew::{html, Callback, Html};
+
+use super::{
+    models::{ChatMessage, Payload},
+    search::{highlight, Matcher},
+};
+
+/// Glyphs of the game font, see the `xivfont` unicode-range in `index.scss`.
+const HQ_ICON: char = '\u{e03c}';
+const MAP_LINK_ICON: char = '\u{e0bb}';
+const AUTO_TRANSLATE_OPEN: char = '\u{e040}';
+const AUTO_TRANSLATE_CLOSE: char = '\u{e041}';
+
+fn copy_to_clipboard(text: String) -> Callback<yew::MouseEvent> {
+    Callback::from(move |_| {
+        if let Some(clipboard) = web_sys::window().and_then(|w| w.navigator().clipboard()) {
+            let _ = clipboard.write_text(&text);
+        }
+    })
+}
+
+/// The payloads `msg` is shown by instead of its text. Without them or with ones unknown to
+/// this page the flattened text is shown, it still has what they stand for.
+pub fn shown_payloads(msg: &ChatMessage) -> Option<&[Payload]> {
+    msg.payloads
+        .as_deref()
+        .filter(|payloads| !payloads.is_empty() && !payloads.contains(&Payload::Unknown))
+}
+
+/// Renders the structured payloads of a message, highlighting search matches in plain text.
+pub fn view_payloads(payloads: &[Payload], matcher: Option<&Matcher>) -> Html {
+    payloads
+        .iter()
+        .map(|payload| view_payload(payload, matcher))
+        .collect()
+}
+
+fn view_payload(payload: &Payload, matcher: Option<&Matcher>) -> Html {
+    match payload {
+        Payload::Text { text } => match matcher {
+            Some(matcher) => highlight(text, &matcher.highlights(text)),
+            None => html! { text.clone() },
+        },
+        Payload::Item { id, name, hq } => {
+            let label = if *hq {
+                format!("{name}{HQ_ICON}")
+            } else {
+                name.clone()
+            };
+            html! {
+                <span class="payload item" title={format!("Item #{id}, click to copy the name")}
+                    onclick={copy_to_clipboard(name.clone())}>{ format!("[{label}]") }</span>
+            }
+        }
+        Payload::MapLink { place_name, x, y } => {
+            let location = format!("{place_name} ( {x:.1} , {y:.1} )");
+            html! {
+                <span class="payload mapLink" title="Click to copy the coordinates"
+                    onclick={copy_to_clipboard(location.clone())}>{ format!("{MAP_LINK_ICON}{location}") }</span>
+            }
+        }
+        Payload::Player { name, world } => {
+            let target = match world {
+                Some(world) => format!("{name}@{world}"),
+                None => name.clone(),
+            };
+            html! {
+                <span class="payload player" title={format!("{target}, click to copy")}
+                    onclick={copy_to_clipboard(target.clone())}>{ name.clone() }</span>
+            }
+        }
+        Payload::AutoTranslate { text } => html! {
+            <span class="payload autoTranslate" title="Auto-translate">
+                { format!("{AUTO_TRANSLATE_OPEN}{text}{AUTO_TRANSLATE_CLOSE}") }
+            </span>
+        },
+        Payload::Icon { id } => html! {
+            <span class="payload icon" title={format!("Icon #{id}")}>{ "◇" }</span>
+        },
+        // messages with these are shown by their text instead, see `shown_payloads`
+        Payload::Unknown => html! {},
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        super::test_support::{message, TIMESTAMP},
+        shown_payloads, Payload,
+    };
+
+    #[test]
+    fn test_shown_payloads() {
+        let mut msg = message(TIMESTAMP, "Shiva");
+        assert_eq!(shown_payloads(&msg), None);
+        msg.payloads = Some(Vec::new());
+        assert_eq!(shown_payloads(&msg), None);
+
+        let player = Payload::Player {
+            name: "Shiva".to_owned(),
+            world: None,
+        };
+        msg.payloads = Some(vec![player.clone()]);
+        assert_eq!(shown_payloads(&msg), Some(&[player.clone()][..]));
+        // added to the plugin after this page was built
+        msg.payloads = Some(vec![player, Payload::Unknown]);
+        assert_eq!(shown_payloads(&msg), None);
+    }
+}
@@ -0,0 +1,181 @@
+use std::ops::Range;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+use yew::{html, Html};
+
+use super::models::{ChatMessage, ChatType};
+
+/// Format of `datetime-local` inputs.
+const INPUT_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+/// Search criteria as entered in the search bar.
+#[derive(Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub text: String,
+    pub regex: bool,
+    pub sender: String,
+    pub channel: Option<ChatType>,
+    pub from: String,
+    pub to: String,
+}
+
+pub enum SearchUpdate {
+    Text(String),
+    Regex(bool),
+    Sender(String),
+    Channel(Option<ChatType>),
+    From(String),
+    To(String),
+}
+
+/// Compiled form of a `SearchQuery`.
+pub struct Matcher {
+    pattern: Option<Regex>,
+    sender: String,
+    channel: Option<ChatType>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+
+impl SearchQuery {
+    pub fn apply(&mut self, update: SearchUpdate) {
+        match update {
+            SearchUpdate::Text(text) => self.text = text,
+            SearchUpdate::Regex(regex) => self.regex = regex,
+            SearchUpdate::Sender(sender) => self.sender = sender,
+            SearchUpdate::Channel(channel) => self.channel = channel,
+            SearchUpdate::From(from) => self.from = from,
+            SearchUpdate::To(to) => self.to = to,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+            && self.sender.is_empty()
+            && self.channel.is_none()
+            && self.from.is_empty()
+            && self.to.is_empty()
+    }
+
+    pub fn compile(&self) -> Result<Matcher, String> {
+        let pattern = if self.text.is_empty() {
+            None
+        } else {
+            let pattern = if self.regex {
+                self.text.clone()
+            } else {
+                regex::escape(&self.text)
+            };
+            Some(Regex::new(&format!("(?i){pattern}")).map_err(|e| format!("Invalid regex: {e}"))?)
+        };
+
+        Ok(Matcher {
+            pattern,
+            sender: self.sender.to_lowercase(),
+            channel: self.channel.clone(),
+            from: parse_input_time(&self.from)?,
+            to: parse_input_time(&self.to)?,
+        })
+    }
+}
+
+fn parse_input_time(value: &str) -> Result<Option<DateTime<Utc>>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let naive = NaiveDateTime::parse_from_str(value, INPUT_TIME_FORMAT)
+        .map_err(|e| format!("Invalid time \"{value}\": {e}"))?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|local| Some(local.with_timezone(&Utc)))
+        .ok_or_else(|| format!("Invalid local time \"{value}\""))
+}
+
+impl Matcher {
+    pub fn matches(&self, msg: &ChatMessage) -> bool {
+        if self
+            .channel
+            .as_ref()
+            .is_some_and(|channel| *channel != msg.chat_type)
+        {
+            return false;
+        }
+        if !self.sender.is_empty() && !msg.sender_name.to_lowercase().contains(&self.sender) {
+            return false;
+        }
+        if self.from.is_some() || self.to.is_some() {
+            let Some(timestamp) = msg.parsed_timestamp() else {
+                return false;
+            };
+            if self.from.is_some_and(|from| timestamp < from)
+                || self.to.is_some_and(|to| timestamp > to)
+            {
+                return false;
+            }
+        }
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(&msg.text))
+    }
+
+    /// Byte ranges of `text` matched by the text criteria.
+    pub fn highlights(&self, text: &str) -> Vec<Range<usize>> {
+        self.pattern.as_ref().map_or_else(Vec::new, |pattern| {
+            pattern
+                .find_iter(text)
+                .filter(|m| !m.is_empty())
+                .map(|m| m.range())
+                .collect()
+        })
+    }
+}
+
+/// Renders `text` with the given ranges wrapped in `<mark>`.
+pub fn highlight(text: &str, ranges: &[Range<usize>]) -> Html {
+    let mut parts = Vec::new();
+    let mut pos = 0;
+    for range in ranges {
+        if range.start > pos {
+            parts.push(html! { { &text[pos..range.start] } });
+        }
+        parts.push(html! { <mark>{ &text[range.clone()] }</mark> });
+        pos = range.end;
+    }
+    if pos < text.len() {
+        parts.push(html! { { &text[pos..] } });
+    }
+    parts.into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        super::test_support::{message, TIMESTAMP},
+        ChatType, SearchQuery,
+    };
+
+    #[test]
+    fn test_search() {
+        let msg = message(TIMESTAMP, "Anyone up for Savage tonight?");
+        let mut query = SearchQuery {
+            text: "savage".to_owned(),
+            ..Default::default()
+        };
+        let matcher = query.compile().unwrap();
+        assert!(matcher.matches(&msg));
+        assert_eq!(matcher.highlights(&msg.text), vec![14..20]);
+
+        query.regex = true;
+        query.text = "up (for|to)".to_owned();
+        query.sender = "NO".to_owned();
+        assert!(query.compile().unwrap().matches(&msg));
+
+        query.channel = Some(ChatType::FreeCompany);
+        assert!(!query.compile().unwrap().matches(&msg));
+
+        query.text = "(".to_owned();
+        assert!(query.compile().is_err());
+    }
+}
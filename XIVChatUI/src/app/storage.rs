@@ -0,0 +1,69 @@
+use serde::{de::DeserializeOwned, Serialize};
+use web_sys::Storage;
+
+fn local_storage() -> Option<Storage> {
+    web_sys::window().and_then(|w| w.local_storage().ok().flatten())
+}
+
+fn session_storage() -> Option<Storage> {
+    web_sys::window().and_then(|w| w.session_storage().ok().flatten())
+}
+
+/// Reads a JSON value from the browser's local storage.
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    load_from(local_storage()?, key)
+}
+
+/// Reads a JSON value from the browser's session storage.
+pub fn load_session<T: DeserializeOwned>(key: &str) -> Option<T> {
+    load_from(session_storage()?, key)
+}
+
+fn load_from<T: DeserializeOwned>(storage: Storage, key: &str) -> Option<T> {
+    let value = storage.get_item(key).ok().flatten()?;
+    serde_json::from_str(&value)
+        .map_err(|e| log::warn!("Ignoring stored {key}: {e}"))
+        .ok()
+}
+
+/// Writes a value to the browser's local storage as JSON.
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    let Some(storage) = local_storage() else {
+        log::warn!("Local storage unavailable, unable to save {key}");
+        return;
+    };
+    save_to(&storage, key, value);
+}
+
+/// Writes a value to the browser's session storage as JSON, it is gone once the tab is
+/// closed.
+pub fn save_session<T: Serialize>(key: &str, value: &T) {
+    let Some(storage) = session_storage() else {
+        log::warn!("Session storage unavailable, unable to save {key}");
+        return;
+    };
+    save_to(&storage, key, value);
+}
+
+fn save_to<T: Serialize>(storage: &Storage, key: &str, value: &T) {
+    match serde_json::to_string(value) {
+        Ok(value) => {
+            if storage.set_item(key, &value).is_err() {
+                log::error!("Unable to save {key}");
+            }
+        }
+        Err(e) => log::error!("Unable to serialize {key}: {e}"),
+    }
+}
+
+pub fn remove(key: &str) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.remove_item(key);
+    }
+}
+
+pub fn remove_session(key: &str) {
+    if let Some(storage) = session_storage() {
+        let _ = storage.remove_item(key);
+    }
+}
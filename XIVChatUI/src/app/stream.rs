@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{Event, EventSource, MessageEvent};
+use yew::Callback;
+
+use super::{bridges::Bridge, models::ChatMessage, requests::url, ErrorKind, ErrorMessage};
+
+const STREAM_URI: &str = "/messages/stream";
+const MAX_BACKOFF_SEC: u64 = 60;
+
+pub enum StreamEvent {
+    Opened,
+    Message(ChatMessage),
+    Closed,
+}
+
+/// Server-Sent Events subscription to the bridge. Every `data` frame is expected to be a single
+/// JSON encoded `ChatMessage`. The connection is closed when this is dropped.
+pub struct MessageStream {
+    source: EventSource,
+    _on_open: Closure<dyn FnMut(Event)>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_error: Closure<dyn FnMut(Event)>,
+}
+
+impl MessageStream {
+    /// Subscribes to `bridge`, or the one serving the page if `None`. EventSource cannot send
+    /// the `Authorization` header, so this only works with bridges that need no token.
+    pub fn connect(
+        bridge: Option<&Bridge>,
+        on_event: Callback<StreamEvent>,
+    ) -> Result<Self, ErrorMessage> {
+        let url = url(bridge, STREAM_URI)?;
+        let source = EventSource::new(url.as_str()).map_err(|e| ErrorMessage {
+            kind: ErrorKind::Connection,
+            description: "Unable to open message stream".to_owned(),
+            details: e.as_string(),
+        })?;
+
+        let on_open = {
+            let on_event = on_event.clone();
+            Closure::<dyn FnMut(Event)>::new(move |_| on_event.emit(StreamEvent::Opened))
+        };
+        let on_message = {
+            let on_event = on_event.clone();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+                let Some(data) = e.data().as_string() else {
+                    return;
+                };
+                match serde_json::from_str::<ChatMessage>(&data) {
+                    Ok(msg) => on_event.emit(StreamEvent::Message(msg)),
+                    Err(e) => log::warn!("Unable to parse streamed message: {e}"),
+                }
+            })
+        };
+        // the browser would silently retry on its own, but we want to fall back to polling
+        // while the stream is down and control the backoff ourselves.
+        let on_error = {
+            let source = source.clone();
+            Closure::<dyn FnMut(Event)>::new(move |_| {
+                source.close();
+                on_event.emit(StreamEvent::Closed);
+            })
+        };
+
+        source.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        source.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            source,
+            _on_open: on_open,
+            _on_message: on_message,
+            _on_error: on_error,
+        })
+    }
+}
+
+impl Drop for MessageStream {
+    fn drop(&mut self) {
+        self.source.close();
+    }
+}
+
+/// Delay before the given reconnect attempt, doubling up to `MAX_BACKOFF_SEC`.
+pub fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(2u64.saturating_pow(attempt).min(MAX_BACKOFF_SEC))
+}
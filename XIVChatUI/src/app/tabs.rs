@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use super::{models::ChatType, storage};
+
+const STORAGE_KEY: &str = "tabs";
+
+/// Channels that can be toggled per tab.
+pub const FILTER_CHANNELS: &[ChatType] = &[
+    ChatType::Say,
+    ChatType::Shout,
+    ChatType::Yell,
+    ChatType::TellIncoming,
+    ChatType::TellOutgoing,
+    ChatType::Party,
+    ChatType::CrossParty,
+    ChatType::Alliance,
+    ChatType::FreeCompany,
+    ChatType::PvPTeam,
+    ChatType::NoviceNetwork,
+    ChatType::LinkShell1,
+    ChatType::LinkShell2,
+    ChatType::LinkShell3,
+    ChatType::LinkShell4,
+    ChatType::LinkShell5,
+    ChatType::LinkShell6,
+    ChatType::LinkShell7,
+    ChatType::LinkShell8,
+    ChatType::CrossLinkShell1,
+    ChatType::CrossLinkShell2,
+    ChatType::CrossLinkShell3,
+    ChatType::CrossLinkShell4,
+    ChatType::CrossLinkShell5,
+    ChatType::CrossLinkShell6,
+    ChatType::CrossLinkShell7,
+    ChatType::CrossLinkShell8,
+    ChatType::StandardEmote,
+    ChatType::CustomEmote,
+    ChatType::Echo,
+    ChatType::SystemMessage,
+    ChatType::SystemError,
+    ChatType::ErrorMessage,
+    ChatType::GatheringSystemMessage,
+    ChatType::NPCDialogue,
+    ChatType::NPCDialogueAnnouncements,
+    ChatType::RetainerSale,
+];
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChatTab {
+    pub name: String,
+    /// Channels shown in this tab, an empty list shows everything.
+    pub channels: Vec<ChatType>,
+    #[serde(skip)]
+    pub unread: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TabSettings {
+    pub tabs: Vec<ChatTab>,
+    pub active: usize,
+}
+
+impl ChatTab {
+    pub fn new(name: &str, channels: Vec<ChatType>) -> Self {
+        Self {
+            name: name.to_owned(),
+            channels,
+            unread: 0,
+        }
+    }
+
+    pub fn shows(&self, chat_type: &ChatType) -> bool {
+        self.channels.is_empty() || self.channels.contains(chat_type)
+    }
+
+    /// The first channel of this tab that messages can be sent to.
+    pub fn primary_channel(&self) -> Option<&ChatType> {
+        self.channels
+            .iter()
+            .find(|chat_type| chat_type.input_channel().is_some())
+    }
+}
+
+impl Default for TabSettings {
+    fn default() -> Self {
+        Self {
+            tabs: vec![
+                ChatTab::new("All", Vec::new()),
+                ChatTab::new("FC", vec![ChatType::FreeCompany]),
+                ChatTab::new("Party", vec![ChatType::Party, ChatType::CrossParty]),
+                ChatTab::new(
+                    "Tells",
+                    vec![ChatType::TellIncoming, ChatType::TellOutgoing],
+                ),
+            ],
+            active: 0,
+        }
+    }
+}
+
+impl TabSettings {
+    pub fn load() -> Self {
+        storage::load::<Self>(STORAGE_KEY)
+            .filter(|settings| settings.active < settings.tabs.len())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(STORAGE_KEY, self);
+    }
+
+    pub fn active_tab(&self) -> &ChatTab {
+        &self.tabs[self.active]
+    }
+
+    pub fn active_tab_mut(&mut self) -> &mut ChatTab {
+        &mut self.tabs[self.active]
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.tabs.len() {
+            self.active = index;
+            self.tabs[index].unread = 0;
+            self.save();
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if self.tabs.len() > 1 && index < self.tabs.len() {
+            self.tabs.remove(index);
+            self.active = self.active.min(self.tabs.len() - 1);
+            self.save();
+        }
+    }
+
+    /// Counts newly received messages as unread for every tab except the active one.
+    pub fn count_unread<'a>(&mut self, new: impl Iterator<Item = &'a ChatType> + Clone) {
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            if index != self.active {
+                tab.unread += new.clone().filter(|chat_type| tab.shows(chat_type)).count();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ChatType, TabSettings};
+
+    #[test]
+    fn test_tab_unread() {
+        let mut tabs = TabSettings::default();
+        let new = [ChatType::FreeCompany, ChatType::Say, ChatType::TellIncoming];
+        tabs.count_unread(new.iter());
+
+        let unread: Vec<usize> = tabs.tabs.iter().map(|tab| tab.unread).collect();
+        assert_eq!(unread, vec![0, 1, 0, 1]);
+        assert_eq!(
+            tabs.tabs[3].primary_channel(),
+            Some(&ChatType::TellOutgoing)
+        );
+    }
+}
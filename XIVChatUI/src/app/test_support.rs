@@ -0,0 +1,20 @@
+use super::models::{ChatMessage, ChatType};
+
+pub const TIMESTAMP: &str = "2024-07-14T10:18:02.8379913+02:00";
+
+/// A message said by no one in particular.
+pub fn message(timestamp: &str, text: &str) -> ChatMessage {
+    chat_message(timestamp, ChatType::Say, "none", text)
+}
+
+pub fn chat_message(timestamp: &str, chat_type: ChatType, sender: &str, text: &str) -> ChatMessage {
+    ChatMessage {
+        timestamp: timestamp.to_owned(),
+        chat_type,
+        sender_name: sender.to_owned(),
+        text: text.to_owned(),
+        payloads: None,
+        server_id: None,
+        source: None,
+    }
+}
@@ -0,0 +1,232 @@
+use std::{cell::Cell, collections::HashMap};
+
+/// Height assumed for rows that were not rendered yet, until the first ones are measured.
+const DEFAULT_ROW_HEIGHT: f64 = 22.0;
+/// Visible height assumed before the chat box was rendered.
+const DEFAULT_HEIGHT: f64 = 1000.0;
+/// Distance from the bottom within which the newest messages are followed.
+const STICK_DISTANCE: f64 = 50.0;
+
+/// Windowed rendering of the chat list, only the rows in and near the visible area are put into
+/// the DOM. Rows are laid out from the bottom, newest first, like in the `column-reverse` chat
+/// box, so all positions are distances from the bottom of the list.
+#[derive(Default)]
+pub struct Viewport {
+    /// Measured heights by row key.
+    heights: HashMap<String, f64>,
+    measured_total: f64,
+    /// Distance of the visible area from the bottom of the list.
+    pub offset: f64,
+    /// Height of the visible area.
+    pub height: f64,
+    /// Bottom-most visible row and how far the visible area starts above its bottom edge, so
+    /// the view stays in place when rows are added or get measured. `None` while the newest
+    /// messages are followed.
+    anchor: Option<(String, f64)>,
+    /// The window of the last render, `view` only has shared access.
+    rendered: Cell<Window>,
+}
+
+/// The rows to render and the space taken by the others.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Window {
+    pub start: usize,
+    pub end: usize,
+    /// Height of the rows before `start`, below the rendered ones.
+    pub below: f64,
+    /// Height of the rows from `end` on, above the rendered ones.
+    pub above: f64,
+}
+
+impl Viewport {
+    fn row_height(&self, key: &str) -> f64 {
+        self.heights.get(key).copied().unwrap_or_else(|| {
+            if self.heights.is_empty() {
+                DEFAULT_ROW_HEIGHT
+            } else {
+                self.measured_total / self.heights.len() as f64
+            }
+        })
+    }
+
+    fn visible_height(&self) -> f64 {
+        if self.height > 0.0 {
+            self.height
+        } else {
+            DEFAULT_HEIGHT
+        }
+    }
+
+    /// Distance of the row at `index` from the bottom of the list.
+    fn position(&self, keys: &[String], index: usize) -> f64 {
+        keys[..index].iter().map(|key| self.row_height(key)).sum()
+    }
+
+    /// The rows covering the visible area and one screen above and below it.
+    pub fn layout(&self, keys: &[String]) -> Window {
+        let height = self.visible_height();
+        let from = self.offset - height;
+        let to = self.offset + 2.0 * height;
+
+        let mut window = Window {
+            start: keys.len(),
+            end: keys.len(),
+            ..Window::default()
+        };
+        let mut position = 0.0;
+        for (index, key) in keys.iter().enumerate() {
+            let row_height = self.row_height(key);
+            if window.start == keys.len() && position + row_height > from {
+                window.start = index;
+                window.below = position;
+            }
+            if position >= to {
+                window.end = index;
+                break;
+            }
+            position += row_height;
+        }
+        window.above = keys[window.end..]
+            .iter()
+            .map(|key| self.row_height(key))
+            .sum();
+        window
+    }
+
+    /// Lays out the rows for rendering.
+    pub fn render(&self, keys: &[String]) -> Window {
+        let window = self.layout(keys);
+        self.rendered.set(window);
+        window
+    }
+
+    /// Whether the visible area came close to the edge of the rendered rows.
+    pub fn needs_render(&self, keys: &[String]) -> bool {
+        let rendered = self.rendered.get();
+        let height = self.visible_height();
+        let rendered_to = self.position(keys, rendered.end.min(keys.len()));
+        (rendered.start > 0 && self.offset - height / 2.0 < rendered.below)
+            || (rendered.end < keys.len() && self.offset + 1.5 * height > rendered_to)
+    }
+
+    pub fn scrolled(&mut self, keys: &[String], offset: f64, height: f64) {
+        self.offset = offset;
+        self.height = height;
+        self.anchor = self.find_anchor(keys);
+    }
+
+    fn find_anchor(&self, keys: &[String]) -> Option<(String, f64)> {
+        if self.offset < STICK_DISTANCE {
+            return None;
+        }
+        let mut position = 0.0;
+        for key in keys {
+            let row_height = self.row_height(key);
+            if position + row_height > self.offset {
+                return Some((key.clone(), self.offset - position));
+            }
+            position += row_height;
+        }
+        None
+    }
+
+    /// Records the heights of rendered rows. Returns whether any of them changed.
+    pub fn measure(&mut self, rows: impl IntoIterator<Item = (String, f64)>) -> bool {
+        let mut changed = false;
+        for (key, height) in rows {
+            let previous = self.heights.insert(key, height);
+            self.measured_total += height - previous.unwrap_or_default();
+            changed |= previous.is_none_or(|previous| (previous - height).abs() >= 0.5);
+        }
+        changed
+    }
+
+    /// Drops the heights of rows that were removed from the list.
+    pub fn forget(&mut self, keys: impl IntoIterator<Item = String>) {
+        for key in keys {
+            if let Some(height) = self.heights.remove(&key) {
+                self.measured_total -= height;
+            }
+        }
+    }
+
+    /// Whether the newest messages are followed.
+    pub fn at_bottom(&self) -> bool {
+        self.anchor.is_none()
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.offset = 0.0;
+        self.anchor = None;
+    }
+
+    /// The offset that keeps the anchor row in place, or the bottom if there is none.
+    pub fn anchored_offset(&self, keys: &[String]) -> f64 {
+        let Some((anchor, delta)) = &self.anchor else {
+            return 0.0;
+        };
+        keys.iter()
+            .position(|key| key == anchor)
+            .map_or(self.offset, |index| self.position(keys, index) + delta)
+    }
+
+    /// Centers the row `key` in the visible area. Returns whether it is in the list.
+    pub fn scroll_to(&mut self, keys: &[String], key: &str) -> bool {
+        let Some(index) = keys.iter().position(|k| k == key) else {
+            return false;
+        };
+        let center = self.position(keys, index) + self.row_height(key) / 2.0;
+        self.offset = (center - self.visible_height() / 2.0).max(0.0);
+        self.anchor = self.find_anchor(keys);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Viewport;
+
+    #[test]
+    fn test_viewport() {
+        let keys: Vec<String> = (0..1000).map(|i| format!("row{i}")).collect();
+        let mut viewport = Viewport::default();
+        viewport.measure(keys.iter().map(|key| (key.clone(), 20.0)));
+
+        // at the bottom, with a screen of overscan above
+        viewport.scrolled(&keys, 0.0, 100.0);
+        let window = viewport.render(&keys);
+        assert_eq!((window.start, window.end), (0, 10));
+        assert_eq!(window.below, 0.0);
+        assert_eq!(window.above, 990.0 * 20.0);
+        assert!(!viewport.needs_render(&keys));
+
+        viewport.scrolled(&keys, 1000.0, 100.0);
+        assert!(viewport.needs_render(&keys));
+        let window = viewport.render(&keys);
+        assert_eq!((window.start, window.end), (45, 60));
+        assert_eq!(window.below, 900.0);
+
+        // new rows below and a wrapped row keep the visible rows in place
+        let mut grown: Vec<String> = vec!["new0".to_owned(), "new1".to_owned()];
+        grown.extend(keys.iter().cloned());
+        viewport.measure([
+            ("new0".to_owned(), 20.0),
+            ("new1".to_owned(), 20.0),
+            ("row10".to_owned(), 60.0),
+        ]);
+        assert_eq!(viewport.anchored_offset(&grown), 1000.0 + 40.0 + 40.0);
+
+        // unless the newest messages were in view
+        viewport.scrolled(&keys, 30.0, 100.0);
+        assert!(viewport.at_bottom());
+        assert_eq!(viewport.anchored_offset(&grown), 0.0);
+
+        assert!(viewport.scroll_to(&keys, "row500"));
+        assert_eq!(viewport.offset, 500.0 * 20.0 + 40.0 + 10.0 - 50.0);
+
+        // removed rows no longer count towards the height of unmeasured ones
+        viewport.forget(keys.iter().cloned());
+        viewport.scroll_to_bottom();
+        assert_eq!(viewport.render(&keys).above, 990.0 * 20.0);
+    }
+}